@@ -3,3 +3,17 @@ pub static ROUTE_ACTION_PLAY: &str = "/action/play";
 pub static ROUTE_ACTION_PAUSE: &str = "/action/pause";
 pub static ROUTE_ACTION_RESUME: &str = "/action/resume";
 pub static ROUTE_ACTION_STOP: &str = "/action/stop";
+pub static ROUTE_ACTION_SEEK: &str = "/action/seek";
+pub static ROUTE_ACTION_VOLUME: &str = "/action/volume";
+pub static ROUTE_ACTION_MUTE: &str = "/action/mute";
+pub static ROUTE_ACTION_MODE: &str = "/action/mode";
+pub static ROUTE_ACTION_NEXT: &str = "/action/next";
+pub static ROUTE_ACTION_PREVIOUS: &str = "/action/previous";
+pub static ROUTE_ACTION_CROSSFADE: &str = "/action/crossfade";
+pub static ROUTE_ACTION_AB_LOOP: &str = "/action/ab-loop";
+pub static ROUTE_ACTION_SLEEP: &str = "/action/sleep";
+pub static ROUTE_STATUS: &str = "/status";
+pub static ROUTE_STREAM: &str = "/stream";
+pub static ROUTE_HISTORY: &str = "/history";
+pub static ROUTE_PLAYLIST: &str = "/playlist";
+pub static ROUTE_ANALYSIS: &str = "/analysis";
@@ -1,4 +1,4 @@
-use racros::AutoDebug;
+use racros::{AutoDebug, AutoStr};
 use serde::{Deserialize, Serialize};
 
 /// Operations defined for clients to use in request.
@@ -25,3 +25,318 @@ pub enum RequestAction {
 pub struct AudioRequestModel {
     action: RequestAction,
 }
+
+/// State of the player.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerState {
+    /// Player initialized, nothing played yet.
+    #[str("initial")]
+    Initial,
+
+    /// Playing audio.
+    #[str("playing")]
+    Playing,
+
+    /// Playing audio but paused.
+    #[str("paused")]
+    Paused,
+
+    /// Stopped and waiting for play next one.
+    #[str("stopped")]
+    Stopped,
+}
+
+/// Running mode of the player, decides what to play when the current one finished.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayMode {
+    /// Repeat to play the current playlist.
+    #[str("repeat-playlist")]
+    RepeatPlaylist,
+
+    /// Repeat to play the current music.
+    #[str("repeat-single")]
+    RepeatSingle,
+
+    /// Randomly play music in playlist.
+    #[str("random")]
+    Random,
+
+    /// Play the current playlist in order and stop at the end.
+    #[str("sequential")]
+    Sequential,
+}
+
+/// Metadata of a music.
+#[derive(AutoDebug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataModel {
+    /// Title.
+    pub title: Option<String>,
+
+    /// Artist names.
+    pub artist: Vec<String>,
+
+    /// Album title.
+    pub album: Option<String>,
+
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+}
+
+/// A music in playlist.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MusicModel {
+    /// File path of the music file.
+    pub file_path: String,
+
+    /// File name of the music file.
+    pub file_name: String,
+
+    /// State of the music file.
+    pub state: String,
+
+    /// Metadata of the music, if parsed.
+    pub metadata: Option<MetadataModel>,
+
+    /// Is an internet radio station.
+    #[serde(default)]
+    pub station: bool,
+
+    /// Count of times played to the end.
+    #[serde(default)]
+    pub play_count: u32,
+
+    /// Count of times skipped before the end.
+    #[serde(default)]
+    pub skip_count: u32,
+
+    /// When started playing last time, in milliseconds since unix epoch.
+    ///
+    /// `None` if never played.
+    #[serde(default)]
+    pub last_played_ms: Option<u64>,
+
+    /// When added to playlist, in milliseconds since unix epoch.
+    #[serde(default)]
+    pub added_ms: u64,
+}
+
+/// Field to sort music by.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MusicSortKey {
+    /// Order in playlist.
+    #[default]
+    #[str("index")]
+    Index,
+
+    /// File name.
+    #[str("name")]
+    Name,
+
+    /// [`MusicModel::play_count`].
+    #[str("play-count")]
+    PlayCount,
+
+    /// [`MusicModel::skip_count`].
+    #[str("skip-count")]
+    SkipCount,
+
+    /// [`MusicModel::last_played_ms`], never played ones are the earliest.
+    #[str("last-played")]
+    LastPlayed,
+
+    /// [`MusicModel::added_ms`].
+    #[str("added")]
+    Added,
+}
+
+/// Contents of a playlist, replied by the server side on playlist request.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistModel {
+    /// Name of the playlist.
+    pub name: String,
+
+    /// All music in playlist.
+    pub entries: Vec<PlaylistEntryModel>,
+}
+
+/// A music in [`PlaylistModel`].
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistEntryModel {
+    /// Index in playlist.
+    pub index: usize,
+
+    /// The music.
+    pub music: MusicModel,
+}
+
+/// Status of the player, replied by the server side on status request.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStatusModel {
+    /// Current state.
+    pub state: PlayerState,
+
+    /// Current playing music.
+    ///
+    /// `None` if no music played ever.
+    pub music: Option<MusicModel>,
+
+    /// Elapsed time of current playing music in milliseconds.
+    pub elapsed_ms: u64,
+
+    /// Total duration of current playing music in milliseconds.
+    ///
+    /// `None` if unknown.
+    pub total_ms: Option<u64>,
+
+    /// Current play mode.
+    pub play_mode: PlayMode,
+
+    /// Crossfade length in milliseconds, zero if disabled.
+    pub crossfade_ms: u64,
+
+    /// Crossfade also applies when repeating the same music in repeat single mode.
+    pub crossfade_repeat_single: bool,
+
+    /// Volume in percent.
+    pub volume: u32,
+
+    /// Muted or not.
+    ///
+    /// `volume` is the level to restore when unmuted.
+    pub muted: bool,
+
+    /// Name of current playlist.
+    pub playlist_name: String,
+
+    /// Index of current playing music in playlist.
+    pub playlist_index: Option<usize>,
+
+    /// Count of music in current playlist.
+    pub playlist_length: usize,
+
+    /// Error stopped the player, e.g. no music in playlist can be played.
+    ///
+    /// Cleared once a music started playing.
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// A-B loop in current playing music, `None` if not looping.
+    #[serde(default)]
+    pub ab_loop: Option<AbLoopModel>,
+
+    /// Pending stop of the player, `None` if not going to stop.
+    #[serde(default)]
+    pub sleep: Option<SleepModel>,
+}
+
+/// A section looped in current playing music.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AbLoopModel {
+    /// Loop start in milliseconds.
+    pub start_ms: u64,
+
+    /// Loop end in milliseconds.
+    pub end_ms: u64,
+}
+
+/// When the player is going to stop, with volume faded out before stopped.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepModel {
+    /// Stop at the end of current playing music, instead of after a timer.
+    pub after_current: bool,
+
+    /// Time left before stopped in milliseconds.
+    ///
+    /// `None` if stopping after current music with unknown duration.
+    pub remaining_ms: Option<u64>,
+}
+
+/// What happened to a music in listening history.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HistoryEvent {
+    /// Started playing.
+    #[str("start")]
+    Start,
+
+    /// Played to the end.
+    #[str("finish")]
+    Finish,
+
+    /// Stopped or switched to another one before the end.
+    #[str("skip")]
+    Skip,
+}
+
+/// A record in listening history.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntryModel {
+    /// When it happened, in milliseconds since unix epoch.
+    pub time_ms: u64,
+
+    /// What happened.
+    pub event: HistoryEvent,
+
+    /// The music.
+    pub music: MusicModel,
+
+    /// How long the music was heard in milliseconds, pauses excluded.
+    ///
+    /// Always zero in [`HistoryEvent::Start`].
+    pub heard_ms: u64,
+}
+
+/// A page of listening history, replied by the server side on history request.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPageModel {
+    /// Count of all records matching the filters.
+    pub total: usize,
+
+    /// Records in this page, the latest one first.
+    pub entries: Vec<HistoryEntryModel>,
+}
+
+/// Levels of a channel in an [`AnalysisModel`].
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelLevelModel {
+    /// Peak amplitude, from 0.0 to 1.0.
+    pub peak: f32,
+
+    /// Root mean square amplitude, from 0.0 to 1.0.
+    pub rms: f32,
+}
+
+/// A frequency band in an [`AnalysisModel`].
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpectrumBandModel {
+    /// Center frequency in Hz.
+    pub frequency: f32,
+
+    /// Level in dBFS, 0.0 is a full scale sine wave.
+    pub level: f32,
+}
+
+/// Levels and spectrum of the audio being played, streamed by the server.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisModel {
+    /// Levels of each channel since the previous analysis.
+    pub channels: Vec<ChannelLevelModel>,
+
+    /// Spectrum of all channels mixed, from low to high frequency.
+    pub spectrum: Vec<SpectrumBandModel>,
+}
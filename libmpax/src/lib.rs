@@ -1,4 +1,4 @@
 #![deny(clippy::all, clippy::pedantic, clippy::nursery)]
 pub mod api;
-mod dto;
+pub mod dto;
 mod single_app;
@@ -0,0 +1,39 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_AB_LOOP;
+
+use crate::client::build_net_client;
+use crate::cmd::AbLoopArgs;
+use crate::duration::parse_duration;
+use crate::url::build_url;
+
+pub async fn handle_ab_loop_command(args: AbLoopArgs) -> Result<()> {
+    let mut url = build_url(ROUTE_ACTION_AB_LOOP);
+    debug!("{} run loop command with args {:#?}", url, args);
+    match (args.start.as_str(), &args.end) {
+        ("off", None) => {}
+        (_, None) => bail!("missing loop end"),
+        (start, Some(end)) => {
+            let start = parse_duration(start)?;
+            let end = parse_duration(end)?;
+            if start >= end {
+                bail!("loop end must be after loop start");
+            }
+            url.query_pairs_mut()
+                .append_pair("start", start.as_millis().to_string().as_str())
+                .append_pair("end", end.as_millis().to_string().as_str());
+        }
+    }
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!(
+            "server replied error: code={:#?}, {}",
+            resp.status(),
+            resp.text().await?
+        )
+    }
+    Ok(())
+}
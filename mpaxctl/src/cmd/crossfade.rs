@@ -0,0 +1,30 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_CROSSFADE;
+
+use crate::client::build_net_client;
+use crate::cmd::CrossfadeArgs;
+use crate::duration::parse_duration;
+use crate::url::build_url;
+
+pub async fn handle_crossfade_command(args: CrossfadeArgs) -> Result<()> {
+    let duration = if args.duration == "off" {
+        0
+    } else {
+        parse_duration(args.duration.as_str())?.as_millis()
+    };
+
+    let mut url = build_url(ROUTE_ACTION_CROSSFADE);
+    debug!("{} run crossfade command with args {:#?}", url, args);
+    url.query_pairs_mut()
+        .append_pair("duration", duration.to_string().as_str())
+        .append_pair("repeatSingle", args.repeat_single.to_string().as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_HISTORY;
+use libmpax::dto::{HistoryEvent, HistoryPageModel};
+
+use crate::client::build_net_client;
+use crate::cmd::HistoryArgs;
+use crate::duration::{format_duration, parse_duration};
+use crate::url::build_url;
+
+pub async fn handle_history_command(args: HistoryArgs) -> Result<()> {
+    if args.page == 0 || args.limit == 0 {
+        bail!("page and limit should be positive");
+    }
+    let mut url = build_url(ROUTE_HISTORY);
+    debug!("{} run history command with args {:#?}", url, args);
+    {
+        let mut query = url.query_pairs_mut();
+        if let Some(since) = &args.since {
+            query.append_pair("since", parse_time(since)?.to_string().as_str());
+        }
+        if let Some(until) = &args.until {
+            query.append_pair("until", parse_time(until)?.to_string().as_str());
+        }
+        query
+            .append_pair(
+                "offset",
+                ((args.page - 1) * args.limit).to_string().as_str(),
+            )
+            .append_pair("limit", args.limit.to_string().as_str());
+    }
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    let page: HistoryPageModel = serde_json::from_str(resp.text().await?.as_str())?;
+    debug!("history: {page:#?}");
+
+    for entry in &page.entries {
+        let time =
+            humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(entry.time_ms));
+        let music = &entry.music;
+        let title = music.metadata.as_ref().and_then(|x| x.title.as_ref());
+        let name = match (title, music.metadata.as_ref().map(|x| x.artist.join(", "))) {
+            (Some(title), Some(artist)) if !artist.is_empty() => format!("{artist} - {title}"),
+            (Some(title), _) => title.clone(),
+            (None, _) => music.file_name.clone(),
+        };
+        match entry.event {
+            HistoryEvent::Start => println!("{time} {:<6} {name}", entry.event.to_string()),
+            HistoryEvent::Finish | HistoryEvent::Skip => println!(
+                "{time} {:<6} {name} (heard {})",
+                entry.event.to_string(),
+                format_duration(Duration::from_millis(entry.heard_ms))
+            ),
+        }
+    }
+    let pages = page.total.div_ceil(args.limit).max(1);
+    println!(
+        "page {}/{}, {} records in total",
+        args.page, pages, page.total
+    );
+    Ok(())
+}
+
+/// Parse time in `value` to milliseconds since unix epoch.
+///
+/// Either a duration ago from now, or a time in utc.
+fn parse_time(value: &str) -> Result<u64> {
+    let time = match parse_duration(value) {
+        Ok(v) => SystemTime::now() - v,
+        Err(_) => humantime::parse_rfc3339_weak(value)
+            .with_context(|| format!("invalid time \"{value}\""))?,
+    };
+    Ok(u64::try_from(time.duration_since(UNIX_EPOCH)?.as_millis())?)
+}
@@ -6,11 +6,35 @@ use clap::{ArgAction, Args, Command, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 use racros::AutoDebug;
 
+use crate::cmd::ab_loop::handle_ab_loop_command;
+use crate::cmd::crossfade::handle_crossfade_command;
+use crate::cmd::history::handle_history_command;
+use crate::cmd::mode::handle_mode_command;
+use crate::cmd::mute::handle_mute_command;
 use crate::cmd::pause::handle_pause_command;
 use crate::cmd::play::handle_play_command;
+use crate::cmd::playlist::handle_playlist_command;
+use crate::cmd::resume::handle_resume_command;
+use crate::cmd::seek::handle_seek_command;
+use crate::cmd::sleep::handle_sleep_command;
+use crate::cmd::status::handle_status_command;
+use crate::cmd::stop::handle_stop_command;
+use crate::cmd::volume::handle_volume_command;
 
+mod ab_loop;
+mod crossfade;
+mod history;
+mod mode;
+mod mute;
 mod pause;
 mod play;
+mod playlist;
+mod resume;
+mod seek;
+mod sleep;
+mod status;
+mod stop;
+mod volume;
 
 ////////////// Args //////////////
 
@@ -29,17 +53,151 @@ pub struct PlayTargetGroup {
         help = "play specified music at given file path"
     )]
     pub file: Option<String>,
+
+    #[arg(
+        short = 'u',
+        long = "url",
+        help = "play network stream at given http(s) url"
+    )]
+    pub url: Option<String>,
 }
 
 #[derive(Args, AutoDebug, Clone)]
 pub struct PlayArgs {
     #[command(flatten)]
     pub play_target: PlayTargetGroup,
+
+    #[arg(
+        short = 's',
+        long = "station",
+        help = "save the url as an internet radio station in playlist, which never ends",
+        requires = "url",
+        action = ArgAction::SetTrue
+    )]
+    pub station: bool,
 }
 
 #[derive(Args, AutoDebug, Clone)]
 pub struct PauseArgs {}
 
+#[derive(Args, AutoDebug, Clone)]
+pub struct SeekArgs {
+    #[arg(
+        help = "position to seek to, e.g. 1:23, 90s; prefix with +/- to seek relatively, e.g. +10s, -30s",
+        allow_hyphen_values = true
+    )]
+    pub position: String,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct VolumeArgs {
+    #[arg(
+        help = "volume in percent from 0 to 100, prefix with +/- to turn up/down, e.g. +5, -10",
+        allow_hyphen_values = true
+    )]
+    pub value: String,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct ModeArgs {
+    #[arg(
+        help = "play mode",
+        value_parser = ["repeat-playlist", "repeat-single", "random", "sequential"]
+    )]
+    pub mode: String,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct CrossfadeArgs {
+    #[arg(help = "crossfade length, e.g. 3s, 0 or off to disable")]
+    pub duration: String,
+
+    #[arg(
+        short = 'r',
+        long = "repeat-single",
+        help = "also crossfade when repeating the same music in repeat-single mode"
+    )]
+    pub repeat_single: bool,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct AbLoopArgs {
+    #[arg(help = "position where the loop starts, e.g. 1:02, or off to stop looping")]
+    pub start: String,
+
+    #[arg(help = "position where the loop ends, e.g. 1:30")]
+    pub end: Option<String>,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct StopArgs {
+    #[arg(
+        short = 'a',
+        long = "after-current",
+        help = "stop at the end of current music instead of now, with volume faded out"
+    )]
+    pub after_current: bool,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct SleepArgs {
+    #[arg(
+        help = "stop after given time with volume faded out, e.g. 30m, 1h; off to cancel, also cancels stop --after-current"
+    )]
+    pub duration: String,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct HistoryArgs {
+    #[arg(
+        long = "since",
+        help = "only show history after given time, a duration ago like 1h, 30m, or a utc time like \"2024-05-01 18:00:00\""
+    )]
+    pub since: Option<String>,
+
+    #[arg(
+        long = "until",
+        help = "only show history before given time, same format as --since"
+    )]
+    pub until: Option<String>,
+
+    #[arg(
+        short = 'n',
+        long = "limit",
+        help = "count of records in each page",
+        default_value_t = 20
+    )]
+    pub limit: usize,
+
+    #[arg(
+        short = 'p',
+        long = "page",
+        help = "page to show, starts from 1 with the latest records",
+        default_value_t = 1
+    )]
+    pub page: usize,
+}
+
+#[derive(Args, AutoDebug, Clone)]
+pub struct PlaylistArgs {
+    #[arg(
+        short = 's',
+        long = "sort",
+        help = "field to sort music by",
+        value_parser = ["index", "name", "play-count", "skip-count", "last-played", "added"],
+        default_value = "index"
+    )]
+    pub sort: String,
+
+    #[arg(
+        short = 'r',
+        long = "reverse",
+        help = "sort in descending order",
+        action = ArgAction::SetTrue
+    )]
+    pub reverse: bool,
+}
+
 #[derive(AutoDebug, Clone, Parser)]
 pub struct MpaxCtlCommand {
     #[command(subcommand)]
@@ -56,7 +214,41 @@ pub enum SubCommand {
 
     Pause(PauseArgs),
 
-    Stop,
+    #[command(about = "Resume paused music")]
+    Resume,
+
+    #[command(about = "Seek in current playing music")]
+    Seek(SeekArgs),
+
+    #[command(about = "Show player status")]
+    Status,
+
+    #[command(about = "Stop playing, now or at the end of current music")]
+    Stop(StopArgs),
+
+    #[command(about = "Stop playing after a while")]
+    Sleep(SleepArgs),
+
+    #[command(about = "Set volume")]
+    Volume(VolumeArgs),
+
+    #[command(about = "Mute or unmute")]
+    Mute,
+
+    #[command(about = "Set play mode")]
+    Mode(ModeArgs),
+
+    #[command(about = "Set crossfade between music")]
+    Crossfade(CrossfadeArgs),
+
+    #[command(about = "Repeat a section of current music")]
+    Loop(AbLoopArgs),
+
+    #[command(about = "Show listening history")]
+    History(HistoryArgs),
+
+    #[command(about = "Show music in current playlist with play statistics")]
+    Playlist(PlaylistArgs),
 
     Exit,
 }
@@ -66,7 +258,18 @@ pub async fn run_command_with_args(command: MpaxCtlCommand) -> Result<()> {
     match command.command.unwrap() {
         SubCommand::Play(args) => handle_play_command(args).await?,
         SubCommand::Pause(args) => handle_pause_command(args).await?,
-        SubCommand::Stop => unimplemented!(),
+        SubCommand::Resume => handle_resume_command().await?,
+        SubCommand::Seek(args) => handle_seek_command(args).await?,
+        SubCommand::Status => handle_status_command().await?,
+        SubCommand::Stop(args) => handle_stop_command(args).await?,
+        SubCommand::Sleep(args) => handle_sleep_command(args).await?,
+        SubCommand::Volume(args) => handle_volume_command(args).await?,
+        SubCommand::Mute => handle_mute_command().await?,
+        SubCommand::Mode(args) => handle_mode_command(args).await?,
+        SubCommand::Crossfade(args) => handle_crossfade_command(args).await?,
+        SubCommand::Loop(args) => handle_ab_loop_command(args).await?,
+        SubCommand::History(args) => handle_history_command(args).await?,
+        SubCommand::Playlist(args) => handle_playlist_command(args).await?,
         SubCommand::Exit => unimplemented!(),
     }
     Ok(())
@@ -0,0 +1,22 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_MODE;
+
+use crate::client::build_net_client;
+use crate::cmd::ModeArgs;
+use crate::url::build_url;
+
+pub async fn handle_mode_command(args: ModeArgs) -> Result<()> {
+    let mut url = build_url(ROUTE_ACTION_MODE);
+    debug!("{} run mode command with args {:#?}", url, args);
+    url.query_pairs_mut()
+        .append_pair("mode", args.mode.as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
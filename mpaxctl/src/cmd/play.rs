@@ -2,7 +2,7 @@ use anyhow::{bail, Result};
 use log::debug;
 use reqwest::StatusCode;
 
-use libmpax::api::ROUTE_ACTION_PLAY;
+use libmpax::api::{ROUTE_ACTION_NEXT, ROUTE_ACTION_PLAY, ROUTE_ACTION_PREVIOUS};
 
 use crate::client::build_net_client;
 use crate::cmd::PlayArgs;
@@ -12,19 +12,33 @@ use crate::url::build_url;
 pub async fn handle_play_command(args: PlayArgs) -> Result<()> {
     let play_target = args.play_target;
     debug!("play: {play_target:#?}");
-    let next = play_target.next.unwrap();
-    let prev = play_target.prev.unwrap();
-    if next || prev {
-        bail!("unsupported play format");
-    }
+    let next = play_target.next.unwrap_or(false);
+    let prev = play_target.prev.unwrap_or(false);
 
-    let file_path = play_target.file.clone().unwrap();
-    let mut url = build_url(ROUTE_ACTION_PLAY);
+    let url = if next {
+        build_url(ROUTE_ACTION_NEXT)
+    } else if prev {
+        build_url(ROUTE_ACTION_PREVIOUS)
+    } else if let Some(file_path) = play_target.file {
+        let mut url = build_url(ROUTE_ACTION_PLAY);
+        url.query_pairs_mut()
+            .append_pair("filePath", file_path.as_str());
+        url
+    } else if let Some(stream_url) = play_target.url {
+        if !stream_url.starts_with("http://") && !stream_url.starts_with("https://") {
+            bail!("unsupported url, only http and https are supported: {stream_url}");
+        }
+        let mut url = build_url(ROUTE_ACTION_PLAY);
+        url.query_pairs_mut()
+            .append_pair("url", stream_url.as_str());
+        if args.station {
+            url.query_pairs_mut().append_pair("station", "true");
+        }
+        url
+    } else {
+        bail!("unsupported play format");
+    };
     debug!("{} run play command", url);
-    let mut query_pairs = url.query_pairs_mut();
-    query_pairs.append_pair("filePath", file_path.as_str());
-    query_pairs.finish();
-    drop(query_pairs);
     let client = build_net_client();
 
     let resp = client.get(url).send().await?;
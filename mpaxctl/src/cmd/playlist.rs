@@ -0,0 +1,57 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_PLAYLIST;
+use libmpax::dto::PlaylistModel;
+
+use crate::client::build_net_client;
+use crate::cmd::PlaylistArgs;
+use crate::url::build_url;
+
+pub async fn handle_playlist_command(args: PlaylistArgs) -> Result<()> {
+    let mut url = build_url(ROUTE_PLAYLIST);
+    debug!("{} run playlist command with args {:#?}", url, args);
+    url.query_pairs_mut()
+        .append_pair("sort", args.sort.as_str())
+        .append_pair("desc", args.reverse.to_string().as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    let playlist: PlaylistModel = serde_json::from_str(resp.text().await?.as_str())?;
+    debug!("playlist: {playlist:#?}");
+
+    println!(
+        "playlist: {} ({} music)",
+        playlist.name,
+        playlist.entries.len()
+    );
+    for entry in &playlist.entries {
+        let music = &entry.music;
+        let title = music.metadata.as_ref().and_then(|x| x.title.as_ref());
+        let name = title.unwrap_or(&music.file_name);
+        let last_played = music.last_played_ms.map_or_else(
+            || String::from("never"),
+            |x| {
+                humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_millis(x)).to_string()
+            },
+        );
+        // States are named after `MusicState` in mpaxd.
+        let state = match music.state.as_str() {
+            "NotExists" => "  [missing]",
+            "Corrupt" => "  [corrupt]",
+            _ => "",
+        };
+        println!(
+            "{:>4}. {name}{state}  plays: {}, skips: {}, last played: {last_played}",
+            entry.index + 1,
+            music.play_count,
+            music.skip_count
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_RESUME;
+
+use crate::client::build_net_client;
+use crate::url::build_url;
+
+pub async fn handle_resume_command() -> Result<()> {
+    let url = build_url(ROUTE_ACTION_RESUME);
+    debug!("{} run resume command", url);
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_SEEK;
+
+use crate::client::build_net_client;
+use crate::cmd::SeekArgs;
+use crate::duration::parse_duration;
+use crate::url::build_url;
+
+pub async fn handle_seek_command(args: SeekArgs) -> Result<()> {
+    let position = args.position.trim();
+    let (key, value) = if let Some(v) = position.strip_prefix('+') {
+        ("offset", i64::try_from(parse_duration(v)?.as_millis())?)
+    } else if let Some(v) = position.strip_prefix('-') {
+        ("offset", -i64::try_from(parse_duration(v)?.as_millis())?)
+    } else {
+        (
+            "position",
+            i64::try_from(parse_duration(position)?.as_millis())?,
+        )
+    };
+
+    let mut url = build_url(ROUTE_ACTION_SEEK);
+    debug!("{} run seek command with args {:#?}", url, args);
+    url.query_pairs_mut()
+        .append_pair(key, value.to_string().as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!(
+            "server replied error: code={:#?}, {}",
+            resp.status(),
+            resp.text().await?
+        )
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_SLEEP;
+
+use crate::client::build_net_client;
+use crate::cmd::SleepArgs;
+use crate::duration::parse_duration;
+use crate::url::build_url;
+
+pub async fn handle_sleep_command(args: SleepArgs) -> Result<()> {
+    let duration = if args.duration == "off" {
+        0
+    } else {
+        let duration = parse_duration(args.duration.as_str())?;
+        if duration.is_zero() {
+            bail!("sleep time must be positive, use off to cancel");
+        }
+        duration.as_millis()
+    };
+
+    let mut url = build_url(ROUTE_ACTION_SLEEP);
+    debug!("{} run sleep command with args {:#?}", url, args);
+    url.query_pairs_mut()
+        .append_pair("duration", duration.to_string().as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
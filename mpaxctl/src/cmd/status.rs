@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_STATUS;
+use libmpax::dto::PlayerStatusModel;
+
+use crate::client::build_net_client;
+use crate::duration::format_duration;
+use crate::url::build_url;
+
+pub async fn handle_status_command() -> Result<()> {
+    let url = build_url(ROUTE_STATUS);
+    debug!("{} run status command", url);
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    let status: PlayerStatusModel = serde_json::from_str(resp.text().await?.as_str())?;
+    debug!("status: {status:#?}");
+
+    println!("state: {}", status.state.to_string());
+    if let Some(error) = &status.error {
+        println!("error: {error}");
+    }
+    if let Some(music) = &status.music {
+        if music.station {
+            println!("station: {} ({})", music.file_name, music.file_path);
+        } else {
+            println!("music: {}", music.file_path);
+        }
+        if let Some(metadata) = &music.metadata {
+            if let Some(title) = &metadata.title {
+                println!("title: {title}");
+            }
+            if !metadata.artist.is_empty() {
+                println!("artist: {}", metadata.artist.join(", "));
+            }
+            if let Some(album) = &metadata.album {
+                println!("album: {album}");
+            }
+        }
+    }
+    let total = status.total_ms.map_or_else(
+        || String::from("-:--"),
+        |x| format_duration(Duration::from_millis(x)),
+    );
+    println!(
+        "time: {} / {}",
+        format_duration(Duration::from_millis(status.elapsed_ms)),
+        total
+    );
+    if let Some(ab_loop) = &status.ab_loop {
+        println!(
+            "loop: {} - {}",
+            format_duration(Duration::from_millis(ab_loop.start_ms)),
+            format_duration(Duration::from_millis(ab_loop.end_ms))
+        );
+    }
+    if let Some(sleep) = &status.sleep {
+        let remaining = sleep.remaining_ms.map_or_else(
+            || String::from("-:--"),
+            |x| format_duration(Duration::from_millis(x)),
+        );
+        if sleep.after_current {
+            println!("sleep: {remaining} (after current)");
+        } else {
+            println!("sleep: {remaining}");
+        }
+    }
+    println!("mode: {}", status.play_mode.to_string());
+    if status.crossfade_ms > 0 {
+        let crossfade = format_duration(Duration::from_millis(status.crossfade_ms));
+        if status.crossfade_repeat_single {
+            println!("crossfade: {crossfade} (also in repeat-single)");
+        } else {
+            println!("crossfade: {crossfade}");
+        }
+    } else {
+        println!("crossfade: off");
+    }
+    if status.muted {
+        println!("volume: {}% (muted)", status.volume);
+    } else {
+        println!("volume: {}%", status.volume);
+    }
+    match status.playlist_index {
+        Some(index) => println!(
+            "playlist: {} [{}/{}]",
+            status.playlist_name,
+            index + 1,
+            status.playlist_length
+        ),
+        None => println!(
+            "playlist: {} [-/{}]",
+            status.playlist_name, status.playlist_length
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use anyhow::{bail, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_STOP;
+
+use crate::client::build_net_client;
+use crate::cmd::StopArgs;
+use crate::url::build_url;
+
+pub async fn handle_stop_command(args: StopArgs) -> Result<()> {
+    let mut url = build_url(ROUTE_ACTION_STOP);
+    debug!("{} run stop command with args {:#?}", url, args);
+    if args.after_current {
+        url.query_pairs_mut().append_pair("afterCurrent", "true");
+    }
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use anyhow::{bail, Context, Result};
+use log::debug;
+use reqwest::StatusCode;
+
+use libmpax::api::ROUTE_ACTION_VOLUME;
+
+use crate::client::build_net_client;
+use crate::cmd::VolumeArgs;
+use crate::url::build_url;
+
+pub async fn handle_volume_command(args: VolumeArgs) -> Result<()> {
+    let value = args.value.trim();
+    let (key, value) = if value.starts_with(['+', '-']) {
+        let step = value
+            .parse::<i16>()
+            .with_context(|| format!("invalid volume step \"{value}\""))?;
+        ("step", step.to_string())
+    } else {
+        let volume = value
+            .parse::<u8>()
+            .ok()
+            .filter(|x| *x <= 100)
+            .with_context(|| format!("invalid volume \"{value}\", expected 0 to 100"))?;
+        ("volume", volume.to_string())
+    };
+
+    let mut url = build_url(ROUTE_ACTION_VOLUME);
+    debug!("{} run volume command with args {:#?}", url, args);
+    url.query_pairs_mut().append_pair(key, value.as_str());
+    let client = build_net_client();
+    let resp = client.get(url).send().await?;
+    if resp.status() != StatusCode::OK {
+        bail!("server replied error: code={:#?}", resp.status())
+    }
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+
+/// Parse a human readable duration.
+///
+/// Supported formats:
+///
+/// * Clock format: `1:23`, `1:02:03`, `83`.
+/// * Unit format: `90s`, `30m`, `1h`, also combined like `1h30m`, `2m10s`.
+///
+/// # Errors
+///
+/// * When `value` is not in any supported format.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("empty duration");
+    }
+    if value.ends_with(['h', 'm', 's']) {
+        return parse_unit_duration(value);
+    }
+
+    let parts = value.split(':').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        bail!("invalid duration \"{value}\": too many ':'");
+    }
+    let mut seconds = 0u64;
+    for part in parts {
+        let v = part
+            .parse::<u64>()
+            .with_context(|| format!("invalid duration \"{value}\""))?;
+        seconds = seconds * 60 + v;
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+fn parse_unit_duration(value: &str) -> Result<Duration> {
+    let mut seconds = 0u64;
+    let mut number = String::new();
+    for ch in value.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+        let scale = match ch {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => bail!("invalid duration \"{value}\": unknown unit '{ch}'"),
+        };
+        if number.is_empty() {
+            bail!("invalid duration \"{value}\": missing number before '{ch}'");
+        }
+        seconds += number.parse::<u64>()? * scale;
+        number.clear();
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format `duration` in clock format, `m:ss` or `h:mm:ss`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1:30").unwrap(), Duration::from_secs(90));
+        assert_eq!(
+            parse_duration("1:02:03").unwrap(),
+            Duration::from_secs(3723)
+        );
+        assert_eq!(parse_duration(" 83 ").unwrap(), Duration::from_secs(83));
+        assert_eq!(parse_duration("0:00").unwrap(), Duration::ZERO);
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+        assert_eq!(parse_duration("1h2m").unwrap(), Duration::from_secs(3720));
+        assert_eq!(parse_duration("2m10s").unwrap(), Duration::from_secs(130));
+    }
+
+    #[test]
+    fn test_parse_duration_error() {
+        for value in [
+            "", " ", "1:2:3:4", "1:", ":30", "1.5", "-10", "abc", "1x", "m", "1hm", "1d2s",
+        ] {
+            assert!(parse_duration(value).is_err(), "{value:?} parsed");
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::ZERO), "0:00");
+        assert_eq!(format_duration(Duration::from_millis(90_999)), "1:30");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59:59");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -7,6 +7,7 @@ use self::cmd::{generate_completion, run_command_with_args, MpaxCtlCommand};
 mod client;
 mod cmd;
 mod config;
+mod duration;
 mod url;
 
 #[allow(clippy::future_not_send)]
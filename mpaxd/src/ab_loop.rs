@@ -0,0 +1,254 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::warn;
+use racros::AutoDebug;
+use rodio::source::SeekError;
+use rodio::Source;
+
+/// Value of [`Shared::end`] when no loop is set.
+const LOOP_OFF: u64 = u64::MAX;
+
+/// State shared between [`AbLoop`] and [`AbLoopSource`], all in microseconds.
+#[derive(AutoDebug, Default)]
+struct Shared {
+    /// Where the loop starts.
+    start: AtomicU64,
+
+    /// Where the loop ends, [`LOOP_OFF`] if no loop set.
+    end: AtomicU64,
+
+    /// Position played in the track.
+    position: AtomicU64,
+}
+
+/// Handle to control the A-B loop of a track and observe its position.
+///
+/// The track is played in an [`AbLoopSource`] sharing the same state.
+#[derive(AutoDebug, Clone)]
+pub struct AbLoop {
+    shared: Arc<Shared>,
+}
+
+impl AbLoop {
+    /// Construct with no loop set.
+    pub fn new() -> Self {
+        let shared = Shared::default();
+        shared.end.store(LOOP_OFF, Ordering::Relaxed);
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Get the loop range, `None` if no loop set.
+    pub fn range(&self) -> Option<(Duration, Duration)> {
+        let end = self.shared.end.load(Ordering::Relaxed);
+        if end == LOOP_OFF {
+            return None;
+        }
+        let start = self.shared.start.load(Ordering::Relaxed);
+        Some((Duration::from_micros(start), Duration::from_micros(end)))
+    }
+
+    /// Loop between `start` and `end`.
+    pub fn set(&self, start: Duration, end: Duration) {
+        self.shared
+            .start
+            .store(duration_to_micros(start), Ordering::Relaxed);
+        self.shared
+            .end
+            .store(duration_to_micros(end), Ordering::Relaxed);
+    }
+
+    /// Remove the loop.
+    pub fn clear(&self) {
+        self.shared.end.store(LOOP_OFF, Ordering::Relaxed);
+    }
+
+    /// Get the position played in the track.
+    ///
+    /// Unlike [`rodio::Sink::get_pos`], this follows jumps back to the loop start.
+    pub fn position(&self) -> Duration {
+        Duration::from_micros(self.shared.position.load(Ordering::Relaxed))
+    }
+
+    fn set_position(&self, position: Duration) {
+        self.shared
+            .position
+            .store(duration_to_micros(position), Ordering::Relaxed);
+    }
+}
+
+/// Source jumping back to the loop start of an [`AbLoop`] once reaches the loop end, without
+/// any gap.
+///
+/// The loop is removed if `input` does not support seeking.
+pub struct AbLoopSource<S> {
+    input: S,
+    ab_loop: AbLoop,
+
+    /// Position where counting [`Self::samples`] started, changed on seeking.
+    offset: Duration,
+
+    /// Samples played since [`Self::offset`].
+    samples: u64,
+}
+
+impl<S> AbLoopSource<S>
+where
+    S: Source<Item = i16>,
+{
+    /// Construct to play `input` under control of `ab_loop`.
+    pub fn new(input: S, ab_loop: AbLoop) -> Self {
+        ab_loop.set_position(Duration::ZERO);
+        Self {
+            input,
+            ab_loop,
+            offset: Duration::ZERO,
+            samples: 0,
+        }
+    }
+
+    /// Restart counting position from `position`.
+    fn reset_position(&mut self, position: Duration) {
+        self.offset = position;
+        self.samples = 0;
+        self.ab_loop.set_position(position);
+    }
+}
+
+impl<S> Iterator for AbLoopSource<S>
+where
+    S: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.input.next()?;
+        self.samples += 1;
+        let channels = u64::from(self.input.channels().max(1));
+        // Only check at the end of each frame to keep channels in order.
+        if self.samples % channels != 0 {
+            return Some(sample);
+        }
+        let frames = u128::from(self.samples / channels);
+        let rate = u128::from(self.input.sample_rate().max(1));
+        let played = u64::try_from(frames * 1_000_000 / rate).unwrap_or(u64::MAX);
+        let position = self.offset + Duration::from_micros(played);
+        self.ab_loop.set_position(position);
+        if let Some((start, end)) = self.ab_loop.range() {
+            if position >= end {
+                match self.input.try_seek(start) {
+                    Ok(()) => self.reset_position(start),
+                    Err(e) => {
+                        warn!("failed to loop back to {start:?}, loop removed: {e}");
+                        self.ab_loop.clear();
+                    }
+                }
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl<S> Source for AbLoopSource<S>
+where
+    S: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.reset_position(pos);
+        Ok(())
+    }
+}
+
+/// Convert `duration` to microseconds, saturate below [`LOOP_OFF`].
+fn duration_to_micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).map_or(LOOP_OFF - 1, |x| x.min(LOOP_OFF - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    /// Source of `frames` frames in 10 frames per second, each sample is its index.
+    fn source(channels: u16, frames: i16, ab_loop: &AbLoop) -> AbLoopSource<SamplesBuffer<i16>> {
+        let samples = (0..frames * i16::try_from(channels).unwrap()).collect::<Vec<_>>();
+        AbLoopSource::new(SamplesBuffer::new(channels, 10, samples), ab_loop.clone())
+    }
+
+    #[test]
+    fn test_loop_back() {
+        let ab_loop = AbLoop::new();
+        ab_loop.set(Duration::from_millis(500), Duration::from_millis(1500));
+        let mut source = source(1, 20, &ab_loop);
+
+        let played = source.by_ref().take(35).collect::<Vec<_>>();
+        let expected = [
+            (0..15).collect::<Vec<_>>(),
+            (5..15).collect(),
+            (5..15).collect(),
+        ];
+        assert_eq!(played, expected.concat());
+        // Already jumped back right after the last sample before the loop end.
+        assert_eq!(ab_loop.position(), Duration::from_millis(500));
+
+        // Played through to the end once the loop is removed.
+        ab_loop.clear();
+        assert_eq!(source.collect::<Vec<_>>(), (5..20).collect::<Vec<_>>());
+        assert_eq!(ab_loop.position(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_loop_back_at_end() {
+        // The loop ends right at the end of the last buffer, never reaches the end.
+        let ab_loop = AbLoop::new();
+        ab_loop.set(Duration::from_secs(1), Duration::from_secs(2));
+        let played = source(1, 20, &ab_loop).take(50).collect::<Vec<_>>();
+        let expected = [
+            (0..20).collect::<Vec<_>>(),
+            (10..20).collect(),
+            (10..20).collect(),
+            (10..20).collect(),
+        ];
+        assert_eq!(played, expected.concat());
+    }
+
+    #[test]
+    fn test_loop_back_in_whole_frames() {
+        let ab_loop = AbLoop::new();
+        ab_loop.set(Duration::from_millis(500), Duration::from_secs(1));
+        let played = source(2, 20, &ab_loop).take(30).collect::<Vec<_>>();
+        let expected = [(0..20).collect::<Vec<_>>(), (10..20).collect()];
+        assert_eq!(played, expected.concat());
+    }
+
+    #[test]
+    fn test_seek_position() {
+        let ab_loop = AbLoop::new();
+        let mut source = source(1, 20, &ab_loop);
+        source.try_seek(Duration::from_millis(1200)).unwrap();
+        assert_eq!(ab_loop.position(), Duration::from_millis(1200));
+        assert_eq!(source.next(), Some(12));
+        assert_eq!(ab_loop.position(), Duration::from_millis(1300));
+    }
+}
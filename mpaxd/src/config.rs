@@ -1 +1,521 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use anyhow::{bail, Context, Result};
+use clap::{ArgAction, Parser};
+use libmpax::api::DEFAULT_SERVER_URL;
+use log::info;
+use racros::{AutoDebug, AutoStr};
+use rust_i18n::t;
+use serde::Deserialize;
+
+/// Name of the default config file in config directory.
+const CONFIG_FILE_NAME: &str = "mpaxd.json";
+
+/// Command line arguments of mpaxd.
+#[derive(AutoDebug, Clone, Parser)]
+pub struct MpaxdCommand {
+    #[arg(
+        short = 'c',
+        long = "config",
+        help = "path of config file, default is $XDG_CONFIG_HOME/mpax/mpaxd.json"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "audio output backend, override the one in config file",
+        value_parser = ["device", "null", "wav"]
+    )]
+    pub output: Option<String>,
+
+    #[arg(
+        long = "fifo",
+        help = "also write raw pcm to the fifo at given path, override the one in config file"
+    )]
+    pub fifo: Option<PathBuf>,
+
+    #[arg(
+        long = "http-stream",
+        help = "also stream audio over http, override the one in config file",
+        action = ArgAction::SetTrue
+    )]
+    pub http_stream: bool,
+
+    #[arg(
+        long = "analyzer",
+        help = "also analyze levels and spectrum of audio for visualizers, override the one in config file",
+        action = ArgAction::SetTrue
+    )]
+    pub analyzer: bool,
+
+    #[arg(
+        short = 'a',
+        long = "address",
+        help = "address for the http server to listen on, override the one in config file"
+    )]
+    pub address: Option<String>,
+
+    #[arg(
+        long = "output-path",
+        help = "path of the file to write in wav output backend, override the one in config file"
+    )]
+    pub output_path: Option<PathBuf>,
+
+    #[arg(
+        long = "auto-play",
+        help = "start playing when restored saved state, override the one in config file",
+        action = ArgAction::SetTrue
+    )]
+    pub auto_play: bool,
+
+    #[arg(
+        long = "replay-gain",
+        help = "ReplayGain mode, override the one in config file",
+        value_parser = ["off", "track", "album"]
+    )]
+    pub replay_gain: Option<String>,
+}
+
+/// Backend to output audio to.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputBackend {
+    /// The default audio device.
+    #[default]
+    #[str("device")]
+    Device,
+
+    /// Consume samples at real-time speed and discard them.
+    #[str("null")]
+    Null,
+
+    /// Write samples into a wav file at real-time speed.
+    #[str("wav")]
+    Wav,
+}
+
+/// Format of raw pcm samples.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PcmFormat {
+    /// Signed 16 bit little endian.
+    #[default]
+    #[str("s16le")]
+    S16,
+
+    /// Signed 32 bit little endian.
+    #[str("s32le")]
+    S32,
+
+    /// 32 bit float little endian.
+    #[str("f32le")]
+    F32,
+}
+
+impl PcmFormat {
+    /// Size of a single sample in bytes.
+    pub const fn sample_size(self) -> usize {
+        match self {
+            Self::S16 => 2,
+            Self::S32 | Self::F32 => 4,
+        }
+    }
+}
+
+/// Config of fifo output, raw pcm is written to the fifo in parallel to the backend.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FifoConfig {
+    /// Path of the fifo, created if not exists.
+    pub path: PathBuf,
+
+    /// Format of samples.
+    pub format: PcmFormat,
+
+    /// Sample rate.
+    pub sample_rate: u32,
+
+    /// Channel count.
+    pub channels: u16,
+}
+
+impl Default for FifoConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("/tmp/mpaxd.fifo"),
+            format: PcmFormat::default(),
+            sample_rate: 44100,
+            channels: 2,
+        }
+    }
+}
+
+/// Config of audio output.
+#[derive(AutoDebug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OutputConfig {
+    /// Backend to use.
+    pub backend: OutputBackend,
+
+    /// Path of the file to write, only used in [`OutputBackend::Wav`].
+    pub path: Option<PathBuf>,
+
+    /// Also write raw pcm to a fifo, disabled if `None`.
+    pub fifo: Option<FifoConfig>,
+
+    /// Also stream audio over http, disabled if `None`.
+    pub http: Option<HttpOutputConfig>,
+
+    /// Also analyze levels and spectrum of audio, disabled if `None`.
+    pub analyzer: Option<AnalyzerConfig>,
+}
+
+/// Config of http stream output, audio is streamed in wav format on the http server in parallel
+/// to the backend.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpOutputConfig {
+    /// Sample rate.
+    pub sample_rate: u32,
+
+    /// Channel count.
+    pub channels: u16,
+}
+
+impl Default for HttpOutputConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            channels: 2,
+        }
+    }
+}
+
+/// Config of audio analyzer, peak and rms levels and spectrum of audio are streamed on the http
+/// server for level meters and visualizers.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalyzerConfig {
+    /// Count of analyses per second.
+    pub rate: u32,
+
+    /// Frames in each fft, must be a power of two.
+    pub fft_size: usize,
+
+    /// Count of frequency bands in spectrum, spaced logarithmically.
+    pub bands: usize,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            rate: 30,
+            fft_size: 2048,
+            bands: 32,
+        }
+    }
+}
+
+/// Config of the http server.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ServerConfig {
+    /// Address to listen on.
+    ///
+    /// Listen on all interfaces to let other machines control the player and listen to the
+    /// http stream.
+    pub address: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_SERVER_URL.to_string(),
+        }
+    }
+}
+
+/// Config of restoring the playback state saved when daemon exited.
+///
+/// Playlist and play statistics are always saved and restored.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResumeConfig {
+    /// Save current music and position on exit and restore them on startup.
+    pub enabled: bool,
+
+    /// Start playing after restored, otherwise stay paused.
+    pub auto_play: bool,
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            auto_play: false,
+        }
+    }
+}
+
+/// Which `ReplayGain` values to apply.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplayGainMode {
+    /// Play as is.
+    #[default]
+    #[str("off")]
+    Off,
+
+    /// Make every track equally loud.
+    #[str("track")]
+    Track,
+
+    /// Make every album equally loud, keep the loudness differences between tracks in album.
+    #[str("album")]
+    Album,
+}
+
+/// Config of `ReplayGain`, adjusting the volume of each music by the gain in its tags.
+///
+/// Music without gain tags are played as is.
+#[derive(AutoDebug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReplayGainConfig {
+    /// Which gain to apply.
+    pub mode: ReplayGainMode,
+
+    /// Extra gain in dB added to the one in tags.
+    pub preamp: f32,
+
+    /// Lower the gain to keep the peak in tags below full scale.
+    pub prevent_clipping: bool,
+}
+
+impl Default for ReplayGainConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplayGainMode::Off,
+            preamp: 0.0,
+            prevent_clipping: true,
+        }
+    }
+}
+
+/// Service to submit listens to.
+#[derive(AutoDebug, AutoStr, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrobbleService {
+    /// `ListenBrainz`, or any server compatible with its api.
+    #[default]
+    #[str("listenbrainz")]
+    ListenBrainz,
+
+    /// Last.fm, or any server compatible with its api.
+    #[str("lastfm")]
+    LastFm,
+}
+
+impl ScrobbleService {
+    /// Url of the official api endpoint.
+    pub const fn default_url(self) -> &'static str {
+        match self {
+            Self::ListenBrainz => "https://api.listenbrainz.org",
+            Self::LastFm => "https://ws.audioscrobbler.com/2.0/",
+        }
+    }
+}
+
+/// Config of scrobbling.
+#[derive(AutoDebug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScrobbleConfig {
+    /// Service to submit listens to.
+    pub service: ScrobbleService,
+
+    /// Url of api endpoint, default is the official one of [`Self::service`].
+    pub url: Option<String>,
+
+    /// User token, required by `ListenBrainz`.
+    #[debug_ignore]
+    pub token: Option<String>,
+
+    /// Api key, required by Last.fm.
+    #[debug_ignore]
+    pub api_key: Option<String>,
+
+    /// Api secret to sign requests, required by Last.fm.
+    #[debug_ignore]
+    pub api_secret: Option<String>,
+
+    /// Session key of the user, required by Last.fm.
+    #[debug_ignore]
+    pub session_key: Option<String>,
+}
+
+impl ScrobbleConfig {
+    /// Get the url of api endpoint.
+    pub fn url(&self) -> &str {
+        self.url
+            .as_deref()
+            .unwrap_or_else(|| self.service.default_url())
+    }
+}
+
+/// Config of mpaxd, loaded from a json file.
+///
+/// All fields are optional in the file.
+#[derive(AutoDebug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Config {
+    /// Audio output.
+    pub output: OutputConfig,
+
+    /// Http server.
+    pub server: ServerConfig,
+
+    /// Restoring playback state.
+    pub resume: ResumeConfig,
+
+    /// `ReplayGain`.
+    pub replay_gain: ReplayGainConfig,
+
+    /// Submit listens to a scrobbling service, disabled if `None`.
+    pub scrobble: Option<ScrobbleConfig>,
+}
+
+impl Config {
+    /// Load config according to command line arguments.
+    ///
+    /// * Load from the file specified in `command`.
+    /// * Otherwise load from the default config file if exists.
+    /// * Otherwise use default values.
+    ///
+    /// Arguments in `command` override values in config file.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to read or parse the config file.
+    /// * When config is invalid.
+    pub fn load(command: &MpaxdCommand) -> Result<Self> {
+        let path = command
+            .config
+            .clone()
+            .or_else(|| default_config_path().filter(|x| x.exists()));
+        let mut config = match path {
+            Some(v) => Self::load_file(&v)?,
+            None => Self::default(),
+        };
+        if let Some(output) = &command.output {
+            // Values are limited by clap.
+            config.output.backend = OutputBackend::try_from(output.as_str()).unwrap();
+        }
+        if let Some(output_path) = &command.output_path {
+            config.output.path = Some(output_path.clone());
+        }
+        if let Some(fifo) = &command.fifo {
+            config
+                .output
+                .fifo
+                .get_or_insert_with(FifoConfig::default)
+                .path
+                .clone_from(fifo);
+        }
+        if command.http_stream && config.output.http.is_none() {
+            config.output.http = Some(HttpOutputConfig::default());
+        }
+        if command.analyzer && config.output.analyzer.is_none() {
+            config.output.analyzer = Some(AnalyzerConfig::default());
+        }
+        if command.auto_play {
+            config.resume.auto_play = true;
+        }
+        if let Some(replay_gain) = &command.replay_gain {
+            // Values are limited by clap.
+            config.replay_gain.mode = ReplayGainMode::try_from(replay_gain.as_str()).unwrap();
+        }
+        if let Some(address) = &command.address {
+            config.server.address.clone_from(address);
+        }
+        if config.output.backend == OutputBackend::Wav && config.output.path.is_none() {
+            bail!(t!("output.wavPathNotSet"));
+        }
+        if let Some(fifo) = &config.output.fifo {
+            if fifo.sample_rate == 0 || fifo.channels == 0 {
+                bail!(t!("output.invalidFifoFormat"));
+            }
+        }
+        if let Some(http) = &config.output.http {
+            if http.sample_rate == 0 || http.channels == 0 {
+                bail!(t!("output.invalidHttpFormat"));
+            }
+        }
+        if let Some(analyzer) = &config.output.analyzer {
+            if analyzer.rate == 0
+                || analyzer.bands == 0
+                || analyzer.fft_size < 64
+                || !analyzer.fft_size.is_power_of_two()
+            {
+                bail!(t!("output.invalidAnalyzerConfig"));
+            }
+        }
+        if !(-15.0..=15.0).contains(&config.replay_gain.preamp) {
+            bail!(t!("replayGain.invalidPreamp"));
+        }
+        if let Some(scrobble) = &config.scrobble {
+            let credentials_set = match scrobble.service {
+                ScrobbleService::ListenBrainz => scrobble.token.is_some(),
+                ScrobbleService::LastFm => {
+                    scrobble.api_key.is_some()
+                        && scrobble.api_secret.is_some()
+                        && scrobble.session_key.is_some()
+                }
+            };
+            if !credentials_set {
+                bail!(t!(
+                    "scrobble.credentialsNotSet",
+                    service = scrobble.service.to_string()
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load config from json file at `path`.
+    fn load_file(path: &Path) -> Result<Self> {
+        info!("load config from {}", path.display());
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        serde_json::from_str(content.as_str())
+            .with_context(|| format!("invalid config file {}", path.display()))
+    }
+}
+
+/// Get the directory to save config files.
+///
+/// `$XDG_CONFIG_HOME/mpax`, or `$HOME/.config/mpax` if `$XDG_CONFIG_HOME` not set.
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+        .map(|x| x.join("mpax"))
+}
+
+/// Get the directory to save state files.
+///
+/// `$XDG_STATE_HOME/mpax`, or `$HOME/.local/state/mpax` if `$XDG_STATE_HOME` not set.
+pub fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".local").join("state")))
+        .map(|x| x.join("mpax"))
+}
+
+/// Get the path of default config file.
+fn default_config_path() -> Option<PathBuf> {
+    config_dir().map(|x| x.join(CONFIG_FILE_NAME))
+}
@@ -0,0 +1,472 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use racros::AutoDebug;
+use rodio::source::SeekError;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::{Metadata, ReplayGain};
+use crate::music::{Music, MusicState, MusicStats};
+
+/// Frames per second in cue sheet timestamps.
+const CUE_FRAMES_PER_SECOND: u64 = 75;
+
+/// A track defined in cue sheet, part of a single audio file.
+#[derive(AutoDebug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CueTrack {
+    /// Path of the cue sheet.
+    pub sheet_path: String,
+
+    /// Path of the audio file containing this track.
+    pub file_path: String,
+
+    /// Track number in cue sheet.
+    pub number: u32,
+
+    /// Where the track starts in audio file.
+    pub start: Duration,
+
+    /// Where the track ends in audio file, `None` if lasts to the end of file.
+    pub end: Option<Duration>,
+}
+
+impl CueTrack {
+    /// Get the length, `None` if lasts to the end of file.
+    pub fn length(&self) -> Option<Duration> {
+        self.end.map(|x| x.saturating_sub(self.start))
+    }
+}
+
+/// Track being parsed in [`parse`].
+#[derive(Default)]
+struct PendingTrack {
+    number: u32,
+    file_path: PathBuf,
+    title: Option<String>,
+    performer: Option<String>,
+
+    /// `ReplayGain` values in `REM` commands of the track.
+    replay_gain: ReplayGain,
+
+    /// Position of `INDEX 00`, start of pregap.
+    pregap: Option<Duration>,
+
+    /// Position of `INDEX 01`.
+    start: Option<Duration>,
+}
+
+/// Parse the cue sheet at `path` into a virtual [`Music`] for each track.
+///
+/// File paths of the [`Music`] are in `<sheet path>#<track number>` format, the audio file and
+/// offsets are in [`Music::cue`].
+///
+/// A track ends where the pregap of next track in same audio file starts, the last track in
+/// each audio file lasts to the end of file.
+///
+/// # Errors
+///
+/// * When failed to read the cue sheet.
+/// * When the cue sheet is invalid or has no track.
+/// * When the audio file referenced not exists.
+pub fn parse(path: &Path) -> Result<Vec<Music>> {
+    let content = fs::read(path).context("failed to read cue sheet")?;
+    parse_sheet(path, &String::from_utf8_lossy(&content))
+}
+
+/// Parse `content` of the cue sheet at `path`, see [`parse`].
+///
+/// Audio files are relative to the directory of `path`.
+fn parse_sheet(path: &Path, content: &str) -> Result<Vec<Music>> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut album = None;
+    let mut album_performer = None;
+    let mut album_replay_gain = ReplayGain::default();
+    let mut file_path = None;
+    let mut tracks: Vec<PendingTrack> = vec![];
+
+    for (line_number, line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                let Some(name) = parse_file_name(args) else {
+                    bail!("invalid FILE at line {}", line_number + 1);
+                };
+                file_path = Some(dir.join(name));
+            }
+            "TRACK" => {
+                let Some(file_path) = file_path.clone() else {
+                    bail!("TRACK before FILE at line {}", line_number + 1);
+                };
+                let mut parts = args.split_whitespace();
+                let number = parts.next().and_then(|x| x.parse().ok());
+                let Some(number) = number else {
+                    bail!("invalid TRACK at line {}", line_number + 1);
+                };
+                // Data tracks have nothing to play.
+                if parts
+                    .next()
+                    .is_some_and(|x| x.eq_ignore_ascii_case("AUDIO"))
+                {
+                    tracks.push(PendingTrack {
+                        number,
+                        file_path,
+                        ..PendingTrack::default()
+                    });
+                }
+            }
+            "TITLE" => match tracks.last_mut() {
+                Some(track) => track.title = Some(unquote(args)),
+                None => album = Some(unquote(args)),
+            },
+            "PERFORMER" => match tracks.last_mut() {
+                Some(track) => track.performer = Some(unquote(args)),
+                None => album_performer = Some(unquote(args)),
+            },
+            "REM" => {
+                let (key, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let value = unquote(value.trim());
+                match tracks.last_mut() {
+                    Some(track) => track.replay_gain.add_tag(key, value.as_str()),
+                    None => album_replay_gain.add_tag(key, value.as_str()),
+                }
+            }
+            "INDEX" => {
+                let Some(track) = tracks.last_mut() else {
+                    continue;
+                };
+                let (index, time) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let Some(time) = parse_time(time.trim()) else {
+                    bail!("invalid INDEX at line {}", line_number + 1);
+                };
+                match index.parse::<u32>() {
+                    Ok(0) => track.pregap = Some(time),
+                    Ok(1) => track.start = Some(time),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let tracks: Vec<PendingTrack> = tracks.into_iter().filter(|x| x.start.is_some()).collect();
+    if tracks.is_empty() {
+        bail!("no audio track in cue sheet");
+    }
+    if let Some(missing) = tracks.iter().find(|x| !x.file_path.is_file()) {
+        bail!("audio file not exists: {}", missing.file_path.display());
+    }
+
+    Ok(build_music(
+        &path.to_string_lossy(),
+        &tracks,
+        album.as_deref(),
+        album_performer.as_deref(),
+        album_replay_gain,
+    ))
+}
+
+/// Build a [`Music`] for each track in cue sheet at `sheet_path`.
+fn build_music(
+    sheet_path: &str,
+    tracks: &[PendingTrack],
+    album: Option<&str>,
+    album_performer: Option<&str>,
+    album_replay_gain: ReplayGain,
+) -> Vec<Music> {
+    tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let start = track.start.unwrap_or_default();
+            let end = tracks
+                .get(i + 1)
+                .filter(|x| x.file_path == track.file_path)
+                .and_then(|x| x.pregap.or(x.start));
+            let cue = CueTrack {
+                sheet_path: sheet_path.to_string(),
+                file_path: track.file_path.to_string_lossy().to_string(),
+                number: track.number,
+                start,
+                end,
+            };
+            let metadata = Metadata::new(
+                track.title.clone(),
+                track
+                    .performer
+                    .clone()
+                    .or_else(|| album_performer.map(ToString::to_string))
+                    .into_iter()
+                    .collect(),
+                album.map(ToString::to_string),
+                cue.length().unwrap_or_default(),
+                ReplayGain {
+                    track_gain: track.replay_gain.track_gain,
+                    track_peak: track.replay_gain.track_peak,
+                    album_gain: album_replay_gain.album_gain,
+                    album_peak: album_replay_gain.album_peak,
+                },
+            );
+            Music {
+                file_path: format!("{}#{:02}", sheet_path, track.number),
+                file_name: track
+                    .file_path
+                    .file_name()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                state: MusicState::Exists,
+                metadata: Some(metadata),
+                station: false,
+                stats: MusicStats::default(),
+                cue: Some(cue),
+            }
+        })
+        .collect()
+}
+
+/// Parse the file name in `FILE "name" TYPE`.
+fn parse_file_name(args: &str) -> Option<String> {
+    if let Some(rest) = args.strip_prefix('"') {
+        return rest.split_once('"').map(|(name, _)| name.to_string());
+    }
+    // Unquoted name, the last word is file type.
+    args.rsplit_once(char::is_whitespace)
+        .map(|(name, _)| name.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Remove surrounding quotes of `value`, if any.
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Parse timestamp in `mm:ss:ff` format, where `ff` is frames in 1/75 second.
+fn parse_time(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':').map(str::parse::<u64>);
+    let (Some(Ok(minutes)), Some(Ok(seconds)), Some(Ok(frames)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    let frames = (minutes * 60 + seconds) * CUE_FRAMES_PER_SECOND + frames;
+    Some(Duration::from_millis(frames * 1000 / CUE_FRAMES_PER_SECOND))
+}
+
+/// Source playing the range of a [`CueTrack`] in `input`.
+///
+/// Positions in seeking are relative to the start of track.
+pub struct CueSource<S> {
+    input: S,
+
+    /// Where the track starts in `input`.
+    start: Duration,
+
+    /// Length of the track, `None` if lasts to the end of `input`.
+    length: Option<Duration>,
+
+    /// Samples left to play, `None` if lasts to the end of `input`.
+    remaining: Option<u64>,
+}
+
+impl<S> CueSource<S>
+where
+    S: Source<Item = i16>,
+{
+    /// Construct and move to the start of `track` in `input`.
+    ///
+    /// Samples before the start are decoded and dropped if `input` does not support seeking.
+    pub fn new(mut input: S, track: &CueTrack) -> Self {
+        if input.try_seek(track.start).is_err() {
+            let skip = duration_to_samples(&input, track.start);
+            for _ in 0..skip {
+                if input.next().is_none() {
+                    break;
+                }
+            }
+        }
+        let length = track.length();
+        let remaining = length.map(|x| duration_to_samples(&input, x));
+        Self {
+            input,
+            start: track.start,
+            length,
+            remaining,
+        }
+    }
+}
+
+impl<S> Iterator for CueSource<S>
+where
+    S: Source<Item = i16>,
+{
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(remaining) = self.remaining.as_mut() {
+            if *remaining == 0 {
+                return None;
+            }
+            *remaining -= 1;
+        }
+        self.input.next()
+    }
+}
+
+impl<S> Source for CueSource<S>
+where
+    S: Source<Item = i16>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let len = self.input.current_frame_len();
+        self.remaining
+            .map(|x| usize::try_from(x).unwrap_or(usize::MAX))
+            .map_or(len, |remaining| {
+                Some(len.map_or(remaining, |x| x.min(remaining)))
+            })
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.length.or_else(|| {
+            self.input
+                .total_duration()
+                .map(|x| x.saturating_sub(self.start))
+        })
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(self.start + pos)?;
+        self.remaining = self
+            .length
+            .map(|x| duration_to_samples(&self.input, x.saturating_sub(pos)));
+        Ok(())
+    }
+}
+
+/// Count of samples in `duration` of `source`, in whole frames.
+fn duration_to_samples<S>(source: &S, duration: Duration) -> u64
+where
+    S: Source<Item = i16>,
+{
+    let frames = u128::from(source.sample_rate()) * duration.as_nanos() / 1_000_000_000;
+    u64::try_from(frames).unwrap_or(u64::MAX) * u64::from(source.channels())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM REPLAYGAIN_ALBUM_GAIN -3.20 dB
+PERFORMER "Album Artist"
+TITLE "Album: Live at Home"
+FILE "disc one.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "First"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second Title"
+    PERFORMER "Guest"
+    INDEX 00 03:58:70
+    INDEX 01 04:00:15
+  TRACK 03 AUDIO
+    TITLE Third
+    INDEX 01 07:30:00
+FILE two.flac WAVE
+  TRACK 04 AUDIO
+    TITLE "Fourth"
+    INDEX 01 00:01:00
+"#;
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(parse_time("01:02:00"), Some(Duration::from_secs(62)));
+        // 15 frames of 1/75 second.
+        assert_eq!(parse_time("04:00:15"), Some(Duration::from_millis(240_200)));
+        assert_eq!(parse_time("00:00:74"), Some(Duration::from_millis(986)));
+        assert_eq!(parse_time("100:00:00"), Some(Duration::from_secs(6000)));
+        assert_eq!(parse_time("01:02"), None);
+        assert_eq!(parse_time("01:02:03:04"), None);
+        assert_eq!(parse_time("aa:02:03"), None);
+    }
+
+    #[test]
+    fn test_parse_sheet() {
+        let dir = std::env::temp_dir().join(format!("mpaxd-cue-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("disc one.flac"), b"").unwrap();
+        fs::write(dir.join("two.flac"), b"").unwrap();
+        let sheet_path = dir.join("album.cue");
+        let sheet = sheet_path.to_string_lossy().to_string();
+
+        let music = parse_sheet(&sheet_path, SHEET).unwrap();
+        let cue = music
+            .iter()
+            .map(|x| x.cue.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            music
+                .iter()
+                .map(|x| x.file_path.as_str())
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 4].map(|x| format!("{sheet}#{x:02}"))
+        );
+        let disc_one = dir.join("disc one.flac").to_string_lossy().to_string();
+        let two = dir.join("two.flac").to_string_lossy().to_string();
+        assert_eq!(
+            cue.iter().map(|x| x.file_path.as_str()).collect::<Vec<_>>(),
+            [disc_one.as_str(), &disc_one, &disc_one, &two]
+        );
+
+        // Ends where the pregap of next track starts.
+        assert_eq!(cue[0].start, Duration::ZERO);
+        assert_eq!(cue[0].end, Some(Duration::from_millis(238_933)));
+        assert_eq!(cue[1].start, Duration::from_millis(240_200));
+        assert_eq!(cue[1].end, Some(Duration::from_secs(450)));
+        // The last track in each file lasts to the end of file.
+        assert_eq!(cue[2].end, None);
+        assert_eq!(cue[3].start, Duration::from_secs(1));
+        assert_eq!(cue[3].end, None);
+
+        let metadata = music
+            .iter()
+            .map(|x| x.metadata.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(metadata[1].title(), Some("Second Title"));
+        assert_eq!(metadata[1].artist(), ["Guest"]);
+        assert_eq!(metadata[2].title(), Some("Third"));
+        assert_eq!(metadata[3].artist(), ["Album Artist"]);
+        assert_eq!(metadata[0].album(), Some("Album: Live at Home"));
+        assert_eq!(metadata[0].duration(), Duration::from_millis(238_933));
+        assert_eq!(metadata[3].replay_gain().album_gain, Some(-3.2));
+
+        fs::remove_file(dir.join("two.flac")).unwrap();
+        assert!(parse_sheet(&sheet_path, SHEET).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_sheet_invalid() {
+        let path = Path::new("/nonexistent/album.cue");
+        assert!(parse_sheet(path, "TRACK 01 AUDIO\n  INDEX 01 00:00:00\n").is_err());
+        assert!(parse_sheet(path, "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n").is_err());
+        assert!(parse_sheet(path, "FILE a.flac WAVE\n  TRACK 01 AUDIO\n  INDEX 01 0:0\n").is_err());
+        assert!(parse_sheet(path, "FILE a.flac WAVE\n  TRACK 01 MODE1/2352\n").is_err());
+    }
+}
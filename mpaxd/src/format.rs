@@ -0,0 +1,256 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use racros::{AutoDebug, AutoStr};
+
+/// Bytes read from the head of file to sniff its format.
+const SNIFF_LEN: usize = 64;
+
+/// Audio file formats recognized when importing music.
+#[derive(AutoDebug, AutoStr, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    #[str("mp3")]
+    Mp3,
+
+    #[str("flac")]
+    Flac,
+
+    /// Vorbis in ogg container.
+    #[str("vorbis")]
+    Vorbis,
+
+    /// Opus in ogg container, no decoder for it.
+    #[str("opus")]
+    Opus,
+
+    #[str("wav")]
+    Wav,
+
+    /// Aac or alac in mp4 container.
+    #[str("m4a")]
+    M4a,
+
+    /// Aac in adts stream.
+    #[str("aac")]
+    Aac,
+}
+
+impl AudioFormat {
+    /// Guess format from the extension of `path`, case insensitive.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "mp3" => Some(Self::Mp3),
+            "flac" => Some(Self::Flac),
+            "ogg" | "oga" => Some(Self::Vorbis),
+            "opus" => Some(Self::Opus),
+            "wav" | "wave" => Some(Self::Wav),
+            "m4a" | "mp4" => Some(Self::M4a),
+            "aac" => Some(Self::Aac),
+            _ => None,
+        }
+    }
+
+    /// Guess format from the leading bytes of file content.
+    ///
+    /// Ogg files are told apart by the codec identification in the first page.
+    pub fn from_content(head: &[u8]) -> Option<Self> {
+        if head.starts_with(b"fLaC") {
+            return Some(Self::Flac);
+        }
+        if head.starts_with(b"OggS") {
+            if contains(head, b"OpusHead") {
+                return Some(Self::Opus);
+            }
+            if contains(head, b"\x01vorbis") {
+                return Some(Self::Vorbis);
+            }
+            return None;
+        }
+        if head.len() >= 12 && head.starts_with(b"RIFF") && &head[8..12] == b"WAVE" {
+            return Some(Self::Wav);
+        }
+        if head.len() >= 8 && &head[4..8] == b"ftyp" {
+            return Some(Self::M4a);
+        }
+        if head.len() >= 2 && head[0] == 0xFF && head[1] & 0xF6 == 0xF0 {
+            // Adts sync word shares the mpeg audio one but layer bits are always zero, which is
+            // reserved in mpeg audio.
+            return Some(Self::Aac);
+        }
+        // Id3v2 tag, or the sync word of an mpeg audio frame with valid version and layer.
+        if head.starts_with(b"ID3")
+            || (head.len() >= 2
+                && head[0] == 0xFF
+                && head[1] & 0xE0 == 0xE0
+                && head[1] & 0x18 != 0x08
+                && head[1] & 0x06 != 0)
+        {
+            return Some(Self::Mp3);
+        }
+        None
+    }
+
+    /// Check whether the format can be decoded by the player.
+    pub const fn is_supported(self) -> bool {
+        !matches!(self, Self::Opus)
+    }
+}
+
+/// Reason why a file is not imported.
+#[derive(AutoDebug, Clone)]
+pub enum SkipReason {
+    /// Neither extension nor content looks like audio.
+    NotAudio,
+
+    /// Content does not match the audio format indicated by extension.
+    Mismatch(AudioFormat),
+
+    /// Recognized audio format but no decoder for it.
+    Unsupported(AudioFormat),
+
+    /// Failed to read the file.
+    Unreadable(String),
+
+    /// Invalid cue sheet.
+    InvalidCue(String),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAudio => write!(f, "not an audio file"),
+            Self::Mismatch(v) => write!(f, "content is not {}", v.to_string()),
+            Self::Unsupported(v) => write!(f, "{} is not supported", v.to_string()),
+            Self::Unreadable(v) => write!(f, "failed to read: {v}"),
+            Self::InvalidCue(v) => write!(f, "invalid cue sheet: {v}"),
+        }
+    }
+}
+
+/// Detect the audio format of file at `path` by content, fallback to extension.
+///
+/// Mp3 has no reliable magic number, files with an `.mp3` extension are accepted even when the
+/// content is not recognized.
+///
+/// # Errors
+///
+/// * When the file is not a supported audio file.
+pub fn detect(path: &Path) -> Result<AudioFormat, SkipReason> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)
+        .and_then(|x| x.take(SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(|e| SkipReason::Unreadable(e.to_string()))?;
+    let format = match (
+        AudioFormat::from_content(&head),
+        AudioFormat::from_extension(path),
+    ) {
+        (Some(v), _) | (None, Some(v @ AudioFormat::Mp3)) => v,
+        (None, Some(v)) => return Err(SkipReason::Mismatch(v)),
+        (None, None) => return Err(SkipReason::NotAudio),
+    };
+    if !format.is_supported() {
+        return Err(SkipReason::Unsupported(format));
+    }
+    Ok(format)
+}
+
+/// Check whether `haystack` contains `needle`.
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|x| x == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAC: &[u8] = b"fLaC\x00\x00\x00\x22";
+    const VORBIS: &[u8] = b"OggS\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x1e\x01vorbis\x00\x00\x00\x00";
+    const OPUS: &[u8] = b"OggS\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x13OpusHead\x01\x02";
+    const WAV: &[u8] = b"RIFF\x24\x08\x00\x00WAVEfmt ";
+    const M4A: &[u8] = b"\x00\x00\x00\x20ftypM4A \x00\x00\x00\x00";
+    const ADTS: &[u8] = b"\xFF\xF1\x50\x80\x02\x1F\xFC";
+    const MP3_ID3: &[u8] = b"ID3\x04\x00\x00\x00\x00\x00\x00";
+    const MP3_FRAME: &[u8] = b"\xFF\xFB\x90\x64\x00\x00";
+    const TEXT: &[u8] = b"not audio at all";
+
+    #[test]
+    fn test_from_content() {
+        assert_eq!(AudioFormat::from_content(FLAC), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::from_content(VORBIS), Some(AudioFormat::Vorbis));
+        assert_eq!(AudioFormat::from_content(OPUS), Some(AudioFormat::Opus));
+        assert_eq!(AudioFormat::from_content(WAV), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::from_content(M4A), Some(AudioFormat::M4a));
+        assert_eq!(AudioFormat::from_content(ADTS), Some(AudioFormat::Aac));
+        assert_eq!(AudioFormat::from_content(MP3_ID3), Some(AudioFormat::Mp3));
+        assert_eq!(AudioFormat::from_content(MP3_FRAME), Some(AudioFormat::Mp3));
+
+        assert_eq!(AudioFormat::from_content(TEXT), None);
+        assert_eq!(AudioFormat::from_content(b""), None);
+        assert_eq!(AudioFormat::from_content(b"OggS\x00\x02"), None);
+        assert_eq!(AudioFormat::from_content(b"RIFF\x24\x08\x00\x00AVI "), None);
+        // Reserved mpeg version and layer.
+        assert_eq!(AudioFormat::from_content(b"\xFF\xEB\x90"), None);
+        assert_eq!(AudioFormat::from_content(b"\xFF\xE1\x90"), None);
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(
+            AudioFormat::from_extension(Path::new("a/b.FLAC")),
+            Some(AudioFormat::Flac)
+        );
+        assert_eq!(
+            AudioFormat::from_extension(Path::new("b.oga")),
+            Some(AudioFormat::Vorbis)
+        );
+        assert_eq!(
+            AudioFormat::from_extension(Path::new("b.mp4")),
+            Some(AudioFormat::M4a)
+        );
+        assert_eq!(AudioFormat::from_extension(Path::new("b.txt")), None);
+        assert_eq!(AudioFormat::from_extension(Path::new("mp3")), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = std::env::temp_dir().join(format!("mpaxd-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let detect_file = |name: &str, content: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            detect(&path)
+        };
+
+        assert!(matches!(detect_file("a.flac", FLAC), Ok(AudioFormat::Flac)));
+        // Content wins over a wrong extension.
+        assert!(matches!(detect_file("b.mp3", WAV), Ok(AudioFormat::Wav)));
+        assert!(matches!(detect_file("c.ogg", M4A), Ok(AudioFormat::M4a)));
+        // Mp3 without tags is accepted by extension.
+        assert!(matches!(detect_file("d.mp3", TEXT), Ok(AudioFormat::Mp3)));
+
+        assert!(matches!(
+            detect_file("e.flac", TEXT),
+            Err(SkipReason::Mismatch(AudioFormat::Flac))
+        ));
+        assert!(matches!(
+            detect_file("f.wav", b""),
+            Err(SkipReason::Mismatch(AudioFormat::Wav))
+        ));
+        assert!(matches!(
+            detect_file("g.txt", TEXT),
+            Err(SkipReason::NotAudio)
+        ));
+        assert!(matches!(detect_file("h", b""), Err(SkipReason::NotAudio)));
+        assert!(matches!(
+            detect_file("i.ogg", OPUS),
+            Err(SkipReason::Unsupported(AudioFormat::Opus))
+        ));
+        assert!(matches!(
+            detect(&dir.join("missing.flac")),
+            Err(SkipReason::Unreadable(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use libmpax::dto::{HistoryEntryModel, HistoryPageModel};
+use log::{info, warn};
+use racros::AutoDebug;
+use rust_i18n::t;
+use serde::Deserialize;
+
+use crate::config::state_dir;
+
+/// Name of the history file in state directory.
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Name of the rotated history file in state directory, holding older records.
+const HISTORY_ROTATED_FILE_NAME: &str = "history.1.jsonl";
+
+/// Size of the history file to rotate at.
+const HISTORY_MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Size of each block read when reading the history file backwards.
+const READ_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Listening history, saved on disk as one json record per line.
+///
+/// Records are appended in time order. When the file grows over [`HISTORY_MAX_SIZE`] it is
+/// rotated, only one rotated file is kept so the oldest records are dropped then.
+#[derive(AutoDebug, Clone)]
+pub struct History {
+    /// Path of the history file, `None` if state directory not found.
+    path: Option<PathBuf>,
+
+    /// Path of the rotated history file.
+    rotated_path: Option<PathBuf>,
+}
+
+/// Time of a history record, parsed alone to count records cheaply.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordTime {
+    time_ms: u64,
+}
+
+/// Filters and paging of a history query.
+#[derive(AutoDebug, Clone)]
+pub struct HistoryQuery {
+    /// Only records at or after this time, in milliseconds since unix epoch.
+    pub since_ms: Option<u64>,
+
+    /// Only records before this time, in milliseconds since unix epoch.
+    pub until_ms: Option<u64>,
+
+    /// Count of latest records to skip.
+    pub offset: usize,
+
+    /// Max count of records to return.
+    pub limit: usize,
+}
+
+impl History {
+    /// Construct with the history file in state directory.
+    pub fn new() -> Self {
+        let dir = state_dir();
+        Self {
+            path: dir.as_ref().map(|x| x.join(HISTORY_FILE_NAME)),
+            rotated_path: dir.map(|x| x.join(HISTORY_ROTATED_FILE_NAME)),
+        }
+    }
+
+    /// Append `entry` to the history file.
+    ///
+    /// # Errors
+    ///
+    /// * When state directory not found.
+    /// * When failed to write the history file.
+    pub fn record(&self, entry: &HistoryEntryModel) -> Result<()> {
+        let path = self.path.as_ref().context(t!("state.stateDirNotFound"))?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .context(t!("history.failedToWrite", path = path.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(t!("history.failedToWrite", path = path.display()))?;
+        file.write_all(line.as_bytes())
+            .context(t!("history.failedToWrite", path = path.display()))?;
+        let size = file
+            .metadata()
+            .context(t!("history.failedToWrite", path = path.display()))?
+            .len();
+        if let Some(rotated_path) = self
+            .rotated_path
+            .as_ref()
+            .filter(|_| size >= HISTORY_MAX_SIZE)
+        {
+            info!("rotate history file {}: {size} bytes", path.display());
+            fs::rename(path, rotated_path)
+                .context(t!("history.failedToWrite", path = rotated_path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Get records matching `query`, the latest one first.
+    ///
+    /// Files are read backwards from the latest record, and reading stops at records before
+    /// [`HistoryQuery::since_ms`]. Records after the page are only counted.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to read the history file.
+    pub fn query(&self, query: &HistoryQuery) -> Result<HistoryPageModel> {
+        let mut total = 0;
+        let mut entries = vec![];
+        for path in [&self.path, &self.rotated_path].into_iter().flatten() {
+            let file = match File::open(path) {
+                Ok(v) => v,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).context(t!("history.failedToRead", path = path.display())),
+            };
+            let mut reached_since = false;
+            read_lines_rev(file, |line| {
+                let in_page = total >= query.offset && entries.len() < query.limit;
+                let record = if in_page {
+                    serde_json::from_slice::<HistoryEntryModel>(line).map(|x| (x.time_ms, Some(x)))
+                } else {
+                    serde_json::from_slice::<RecordTime>(line).map(|x| (x.time_ms, None))
+                };
+                // The last line may be partially written.
+                let (time_ms, entry) = match record {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("skip invalid history record: {e}");
+                        return true;
+                    }
+                };
+                if query.since_ms.is_some_and(|x| time_ms < x) {
+                    reached_since = true;
+                    return false;
+                }
+                if query.until_ms.is_some_and(|x| time_ms >= x) {
+                    return true;
+                }
+                if let Some(entry) = entry {
+                    entries.push(entry);
+                }
+                total += 1;
+                true
+            })
+            .context(t!("history.failedToRead", path = path.display()))?;
+            if reached_since {
+                break;
+            }
+        }
+        Ok(HistoryPageModel { total, entries })
+    }
+}
+
+/// Call `f` on each non-empty line in `file` from the last one, until `f` returns `false`.
+fn read_lines_rev(mut file: File, mut f: impl FnMut(&[u8]) -> bool) -> io::Result<()> {
+    let mut end = file.seek(SeekFrom::End(0))?;
+    let mut block = vec![0u8; READ_BLOCK_SIZE];
+    // Line whose start is not read yet.
+    let mut tail = vec![];
+    while end > 0 {
+        let start = end.saturating_sub(READ_BLOCK_SIZE as u64);
+        let length = usize::try_from(end - start).unwrap_or(READ_BLOCK_SIZE);
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block[..length])?;
+        end = start;
+        let mut data = block[..length].to_vec();
+        data.append(&mut tail);
+        let mut rest = data.as_slice();
+        while let Some(pos) = rest.iter().rposition(|x| *x == b'\n') {
+            let line = &rest[pos + 1..];
+            if !line.is_empty() && !f(line) {
+                return Ok(());
+            }
+            rest = &rest[..pos];
+        }
+        tail = rest.to_vec();
+    }
+    if !tail.is_empty() {
+        f(&tail);
+    }
+    Ok(())
+}
+
+/// The music being listened, recorded in [`History`] when it ends.
+#[derive(AutoDebug)]
+pub struct Listening {
+    /// File path of the music.
+    pub file_path: String,
+
+    /// When started listening, in milliseconds since unix epoch.
+    pub started_at_ms: u64,
+
+    /// Time heard before last pause.
+    heard: Duration,
+
+    /// When resumed last time, `None` if paused.
+    resumed_at: Option<Instant>,
+}
+
+impl Listening {
+    /// Start listening to the music at `file_path`.
+    pub fn new(file_path: String) -> Self {
+        Self {
+            file_path,
+            started_at_ms: now_millis(),
+            heard: Duration::ZERO,
+            resumed_at: Some(Instant::now()),
+        }
+    }
+
+    /// Pause, time since now is not counted as heard.
+    pub fn pause(&mut self) {
+        if let Some(v) = self.resumed_at.take() {
+            self.heard += v.elapsed();
+        }
+    }
+
+    /// Resume after paused.
+    pub fn resume(&mut self) {
+        self.resumed_at.get_or_insert_with(Instant::now);
+    }
+
+    /// Get the total time heard, pauses excluded.
+    pub fn heard(&self) -> Duration {
+        self.heard + self.resumed_at.map_or(Duration::ZERO, |x| x.elapsed())
+    }
+}
+
+/// Get current time in milliseconds since unix epoch.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| u64::try_from(x.as_millis()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_lines_rev() {
+        let path = std::env::temp_dir().join(format!("mpaxd-history-{}", std::process::id()));
+        // Lines crossing block boundaries, an empty line and no trailing newline.
+        let lines = (0..READ_BLOCK_SIZE / 4)
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let content = format!("{}\n\nlast", lines.join("\n"));
+        fs::write(&path, content).unwrap();
+
+        let mut read = vec![];
+        read_lines_rev(File::open(&path).unwrap(), |x| {
+            read.push(String::from_utf8(x.to_vec()).unwrap());
+            true
+        })
+        .unwrap();
+        let mut expected = lines;
+        expected.push("last".to_string());
+        expected.reverse();
+        assert_eq!(read, expected);
+
+        let mut read = vec![];
+        read_lines_rev(File::open(&path).unwrap(), |x| {
+            read.push(String::from_utf8(x.to_vec()).unwrap());
+            read.len() < 3
+        })
+        .unwrap();
+        assert_eq!(read, expected[..3]);
+        fs::remove_file(&path).unwrap();
+    }
+}
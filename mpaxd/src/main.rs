@@ -1,30 +1,94 @@
-#[warn(clippy::all, clippy::pedantic, clippy::nursery)]
 use std::sync::mpsc::channel;
 
 use anyhow::Result;
+use clap::Parser;
+use log::{error, info};
 use rust_i18n::i18n;
+use tokio::signal::unix::{signal, SignalKind};
 
+use crate::config::{Config, MpaxdCommand};
+use crate::history::History;
+use crate::output::{Analyzer, HttpBroadcast, HttpOutputs};
 use crate::player::{launch_player_thread, PlayAction};
+use crate::scrobble::Scrobbler;
 use crate::server::launch_server_thread;
 
 i18n!("i18n");
 
+mod ab_loop;
 mod config;
+mod cue;
+mod format;
+mod history;
 mod metadata;
 mod music;
+mod network;
+mod output;
 mod player;
 mod playlist;
+mod scrobble;
 mod server;
+mod shuffle;
+mod state;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
+    let config = Config::load(&MpaxdCommand::parse())?;
     let (tx, rx) = channel::<PlayAction>();
     let tx2 = tx.clone();
-    let player_thread_handle = tokio::spawn(launch_player_thread(tx, rx));
-    let server_thread_handle = tokio::spawn(launch_server_thread(tx2));
-    player_thread_handle.await??;
-    server_thread_handle.await??;
-    //join_all([player_thread_handle, server_thread_handle]).await;
+    let tx3 = tx.clone();
+    let tx4 = tx.clone();
+    let http = HttpOutputs {
+        stream: config.output.http.clone().map(HttpBroadcast::new),
+        analyzer: config.output.analyzer.clone().map(Analyzer::new),
+    };
+    let history = History::new();
+    let scrobbler = config.scrobble.map(Scrobbler::spawn).transpose()?;
+    let mut player_thread_handle = tokio::spawn(launch_player_thread(
+        tx,
+        rx,
+        config.output,
+        http.clone(),
+        config.resume,
+        history.clone(),
+        scrobbler,
+        config.replay_gain,
+    ));
+    let mut server_thread_handle =
+        tokio::spawn(launch_server_thread(tx2, config.server, http, history));
+    tokio::spawn(async move {
+        if let Err(e) = wait_for_exit_signal().await {
+            error!("failed to listen for exit signal: {e}");
+            return;
+        }
+        info!("exit signal received");
+        // Let the player save state and stop before exit.
+        let _ = tx3.send(PlayAction::Exit);
+    });
+    tokio::select! {
+        v = &mut player_thread_handle => {
+            server_thread_handle.abort();
+            v??;
+        }
+        v = &mut server_thread_handle => {
+            // Server stopped, let the player save state and stop before exit.
+            let _ = tx4.send(PlayAction::Exit);
+            if let Err(e) = player_thread_handle.await? {
+                error!("player stopped with error: {e:#}");
+            }
+            v??;
+        }
+    }
+    Ok(())
+}
+
+/// Wait until received SIGINT or SIGTERM.
+async fn wait_for_exit_signal() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        v = tokio::signal::ctrl_c() => v?,
+        _ = terminate.recv() => {}
+    }
     Ok(())
 }
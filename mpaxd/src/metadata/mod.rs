@@ -1,13 +1,22 @@
 use anyhow::Result;
+use libmpax::dto::MetadataModel;
 use racros::AutoDebug;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+pub use parser::SymphoniaParser;
+pub use replay_gain::{gain_factor, ReplayGain};
+
+mod parser;
+mod replay_gain;
+
 /// Music metadata.
 ///
 /// All fields have relative audio metadata tag in music file.
 ///
 /// Now is representing id3v2 format.
-#[derive(AutoDebug, Clone)]
+#[derive(AutoDebug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Metadata {
     // Title.
     title: Option<String>,
@@ -22,11 +31,69 @@ pub struct Metadata {
 
     /// Duration.
     duration: Duration,
+
+    /// `ReplayGain` values.
+    #[serde(default)]
+    replay_gain: ReplayGain,
+}
+
+impl Metadata {
+    /// Construct.
+    pub const fn new(
+        title: Option<String>,
+        artist: Vec<String>,
+        album: Option<String>,
+        duration: Duration,
+        replay_gain: ReplayGain,
+    ) -> Self {
+        Self {
+            title,
+            artist,
+            album,
+            duration,
+            replay_gain,
+        }
+    }
+
+    /// Get the title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Get all artist names.
+    pub fn artist(&self) -> &[String] {
+        self.artist.as_slice()
+    }
+
+    /// Get the album title.
+    pub fn album(&self) -> Option<&str> {
+        self.album.as_deref()
+    }
+
+    /// Get the duration.
+    pub const fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Get the `ReplayGain` values.
+    pub const fn replay_gain(&self) -> ReplayGain {
+        self.replay_gain
+    }
+}
+
+impl From<&Metadata> for MetadataModel {
+    fn from(value: &Metadata) -> Self {
+        Self {
+            title: value.title().map(ToString::to_string),
+            artist: value.artist().to_vec(),
+            album: value.album().map(ToString::to_string),
+            duration_ms: u64::try_from(value.duration().as_millis()).unwrap_or(u64::MAX),
+        }
+    }
 }
 
 /// Definition the functionality of something that can done whe metadata related works.
-#[allow(unused)]
-trait MetadataParser {
+pub trait MetadataParser {
     /// Parse a single metadata from given [`file_path`].
     fn parse_metadata_from_file(file_path: impl AsRef<str>) -> Result<Metadata>;
 }
@@ -0,0 +1,102 @@
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+use crate::metadata::{Metadata, MetadataParser, ReplayGain};
+
+/// Parse [`Metadata`] from tags in audio file, in any container symphonia supports.
+pub struct SymphoniaParser;
+
+impl MetadataParser for SymphoniaParser {
+    fn parse_metadata_from_file(file_path: impl AsRef<str>) -> Result<Metadata> {
+        let path = Path::new(file_path.as_ref());
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|x| x.to_str()) {
+            hint.with_extension(extension);
+        }
+        let mut probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default()),
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .with_context(|| format!("unsupported format: {}", path.display()))?;
+
+        let mut tags = TagValues::default();
+        // Tags before the container, e.g. id3v2 in mp3, are overridden by the ones in it.
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|x| x.current()) {
+            tags.add_revision(revision);
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            tags.add_revision(revision);
+        }
+        let duration = probed
+            .format
+            .default_track()
+            .and_then(|x| Some((x.codec_params.time_base?, x.codec_params.n_frames?)))
+            .map_or(Duration::ZERO, |(time_base, frames)| {
+                let time = time_base.calc_time(frames);
+                Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+            });
+        Ok(Metadata::new(
+            tags.title,
+            tags.artist,
+            tags.album,
+            duration,
+            tags.replay_gain,
+        ))
+    }
+}
+
+/// Values collected from tags.
+#[derive(Default)]
+struct TagValues {
+    title: Option<String>,
+    artist: Vec<String>,
+    album: Option<String>,
+    replay_gain: ReplayGain,
+}
+
+impl TagValues {
+    fn add_revision(&mut self, revision: &MetadataRevision) {
+        let mut artist = vec![];
+        for tag in revision.tags() {
+            let value = tag.value.to_string();
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => self.title = Some(value),
+                Some(StandardTagKey::Artist) => artist.push(value),
+                Some(StandardTagKey::Album) => self.album = Some(value),
+                // Raw keys differ in containers, e.g. TXXX frames in id3v2.
+                Some(StandardTagKey::ReplayGainTrackGain) => {
+                    self.replay_gain
+                        .add_tag("REPLAYGAIN_TRACK_GAIN", value.as_str());
+                }
+                Some(StandardTagKey::ReplayGainTrackPeak) => {
+                    self.replay_gain
+                        .add_tag("REPLAYGAIN_TRACK_PEAK", value.as_str());
+                }
+                Some(StandardTagKey::ReplayGainAlbumGain) => {
+                    self.replay_gain
+                        .add_tag("REPLAYGAIN_ALBUM_GAIN", value.as_str());
+                }
+                Some(StandardTagKey::ReplayGainAlbumPeak) => {
+                    self.replay_gain
+                        .add_tag("REPLAYGAIN_ALBUM_PEAK", value.as_str());
+                }
+                _ => self.replay_gain.add_tag(tag.key.as_str(), value.as_str()),
+            }
+        }
+        if !artist.is_empty() {
+            self.artist = artist;
+        }
+    }
+}
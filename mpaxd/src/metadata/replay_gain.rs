@@ -0,0 +1,201 @@
+use racros::AutoDebug;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ReplayGainConfig, ReplayGainMode};
+
+/// Loudness R128 gain tags are normalized to, in LUFS.
+const R128_REFERENCE: f32 = -23.0;
+
+/// Loudness `ReplayGain` tags are normalized to, in LUFS.
+const REPLAY_GAIN_REFERENCE: f32 = -18.0;
+
+/// `ReplayGain` values of a music, from `ReplayGain` or R128 tags.
+///
+/// Gains are in dB relative to the `ReplayGain` reference loudness, peaks are linear sample
+/// amplitudes where 1.0 is full scale.
+#[derive(AutoDebug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Save the value of tag `key` if it's a `ReplayGain` or R128 tag, ignore others.
+    ///
+    /// `ReplayGain` tags are preferred, R128 ones only fill the gains not set.
+    pub fn add_tag(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        match key.to_ascii_uppercase().as_str() {
+            "REPLAYGAIN_TRACK_GAIN" => self.track_gain = parse_gain(value).or(self.track_gain),
+            "REPLAYGAIN_TRACK_PEAK" => self.track_peak = parse_peak(value).or(self.track_peak),
+            "REPLAYGAIN_ALBUM_GAIN" => self.album_gain = parse_gain(value).or(self.album_gain),
+            "REPLAYGAIN_ALBUM_PEAK" => self.album_peak = parse_peak(value).or(self.album_peak),
+            "R128_TRACK_GAIN" => self.track_gain = self.track_gain.or_else(|| parse_r128(value)),
+            "R128_ALBUM_GAIN" => self.album_gain = self.album_gain.or_else(|| parse_r128(value)),
+            _ => {}
+        }
+    }
+
+    /// Get the gain and peak to apply in `mode`.
+    ///
+    /// Fall back to the track values in album mode if no album values, and vice versa.
+    ///
+    /// Return `None` if disabled or no gain at all.
+    pub fn select(&self, mode: ReplayGainMode) -> Option<(f32, Option<f32>)> {
+        let track = self.track_gain.map(|x| (x, self.track_peak));
+        let album = self.album_gain.map(|x| (x, self.album_peak));
+        match mode {
+            ReplayGainMode::Off => None,
+            ReplayGainMode::Track => track.or(album),
+            ReplayGainMode::Album => album.or(track),
+        }
+    }
+}
+
+/// Get the factor to amplify samples by to apply `gain` and `peak` selected by
+/// [`ReplayGain::select`], with the preamp in `config`.
+///
+/// Lowered to keep `peak` below full scale if preventing clipping.
+pub fn gain_factor(gain: f32, peak: Option<f32>, config: &ReplayGainConfig) -> f32 {
+    let factor = 10f32.powf((gain + config.preamp) / 20.0);
+    match peak.filter(|x| *x > 0.0) {
+        Some(peak) if config.prevent_clipping => factor.min(1.0 / peak),
+        _ => factor,
+    }
+}
+
+/// Parse gain in `-6.54 dB` format.
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    value.trim().parse().ok().filter(|x: &f32| x.is_finite())
+}
+
+/// Parse linear peak amplitude.
+fn parse_peak(value: &str) -> Option<f32> {
+    value
+        .parse()
+        .ok()
+        .filter(|x: &f32| x.is_finite() && *x >= 0.0)
+}
+
+/// Parse R128 gain, a Q7.8 fixed point number in dB relative to [`R128_REFERENCE`].
+fn parse_r128(value: &str) -> Option<f32> {
+    value
+        .parse::<i16>()
+        .ok()
+        .map(|x| f32::from(x) / 256.0 + REPLAY_GAIN_REFERENCE - R128_REFERENCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay_gain(tags: &[(&str, &str)]) -> ReplayGain {
+        let mut replay_gain = ReplayGain::default();
+        for (key, value) in tags {
+            replay_gain.add_tag(key, value);
+        }
+        replay_gain
+    }
+
+    fn assert_near(left: f32, right: f32) {
+        assert!((left - right).abs() < 1e-3, "{left} != {right}");
+    }
+
+    #[test]
+    fn test_replay_gain_tags() {
+        let gain = replay_gain(&[
+            ("REPLAYGAIN_TRACK_GAIN", "-6.5 dB"),
+            ("replaygain_track_peak", " 0.988553 "),
+            ("REPLAYGAIN_ALBUM_GAIN", "+2.10 db"),
+            ("REPLAYGAIN_ALBUM_PEAK", "1.2"),
+            ("TITLE", "-1 dB"),
+        ]);
+        assert_eq!(gain.track_gain, Some(-6.5));
+        assert_eq!(gain.track_peak, Some(0.988_553));
+        assert_eq!(gain.album_gain, Some(2.1));
+        assert_eq!(gain.album_peak, Some(1.2));
+
+        // Invalid values are ignored, keeping the valid ones.
+        let gain = replay_gain(&[
+            ("REPLAYGAIN_TRACK_GAIN", "-3 dB"),
+            ("REPLAYGAIN_TRACK_GAIN", "loud"),
+            ("REPLAYGAIN_ALBUM_GAIN", "inf dB"),
+            ("REPLAYGAIN_TRACK_PEAK", "-0.5"),
+            ("REPLAYGAIN_ALBUM_PEAK", "NaN"),
+        ]);
+        assert_eq!(gain.track_gain, Some(-3.0));
+        assert_eq!(gain.album_gain, None);
+        assert_eq!(gain.track_peak, None);
+        assert_eq!(gain.album_peak, None);
+    }
+
+    #[test]
+    fn test_r128_tags() {
+        // Q7.8 in dB relative to -23 LUFS, 5 dB quieter than the ReplayGain reference.
+        let gain = replay_gain(&[("R128_TRACK_GAIN", "-1280"), ("R128_ALBUM_GAIN", "384")]);
+        assert_eq!(gain.track_gain, Some(0.0));
+        assert_eq!(gain.album_gain, Some(6.5));
+        assert_eq!(gain.track_peak, None);
+
+        // ReplayGain tags are preferred in whatever order.
+        let gain = replay_gain(&[
+            ("R128_TRACK_GAIN", "0"),
+            ("REPLAYGAIN_TRACK_GAIN", "-6.5 dB"),
+            ("REPLAYGAIN_ALBUM_GAIN", "-1 dB"),
+            ("R128_ALBUM_GAIN", "0"),
+        ]);
+        assert_eq!(gain.track_gain, Some(-6.5));
+        assert_eq!(gain.album_gain, Some(-1.0));
+
+        assert_eq!(replay_gain(&[("R128_TRACK_GAIN", "1.5")]).track_gain, None);
+        assert_eq!(
+            replay_gain(&[("R128_TRACK_GAIN", "40000")]).track_gain,
+            None
+        );
+    }
+
+    #[test]
+    fn test_select() {
+        let track = replay_gain(&[("REPLAYGAIN_TRACK_GAIN", "-6.5 dB")]);
+        let both = replay_gain(&[
+            ("REPLAYGAIN_TRACK_GAIN", "-6.5 dB"),
+            ("REPLAYGAIN_ALBUM_GAIN", "-4 dB"),
+            ("REPLAYGAIN_ALBUM_PEAK", "0.5"),
+        ]);
+        assert_eq!(both.select(ReplayGainMode::Off), None);
+        assert_eq!(both.select(ReplayGainMode::Track), Some((-6.5, None)));
+        assert_eq!(both.select(ReplayGainMode::Album), Some((-4.0, Some(0.5))));
+        // Fall back to the other one.
+        assert_eq!(track.select(ReplayGainMode::Album), Some((-6.5, None)));
+        assert_eq!(ReplayGain::default().select(ReplayGainMode::Track), None);
+    }
+
+    #[test]
+    fn test_gain_factor() {
+        let mut config = ReplayGainConfig {
+            mode: ReplayGainMode::Track,
+            preamp: 0.0,
+            prevent_clipping: true,
+        };
+        assert_near(gain_factor(0.0, None, &config), 1.0);
+        assert_near(gain_factor(-6.0206, Some(0.5), &config), 0.5);
+        config.preamp = 6.0206;
+        assert_near(gain_factor(0.0, Some(0.25), &config), 2.0);
+
+        // Lowered to keep the peak at full scale.
+        assert_near(gain_factor(0.0, Some(0.8), &config), 1.25);
+        assert_near(gain_factor(0.0, Some(1.25), &config), 0.8);
+        // Zero peak is unknown.
+        assert_near(gain_factor(0.0, Some(0.0), &config), 2.0);
+
+        config.prevent_clipping = false;
+        assert_near(gain_factor(0.0, Some(1.25), &config), 2.0);
+    }
+}
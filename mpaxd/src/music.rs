@@ -1,9 +1,12 @@
+use libmpax::dto::MusicModel;
 use racros::{AutoDebug, AutoStr};
+use serde::{Deserialize, Serialize};
 
+use crate::cue::CueTrack;
 use crate::metadata::Metadata;
 
 /// Enum indicating music's status.
-#[derive(AutoDebug, AutoStr, Clone, Default, PartialEq, Eq)]
+#[derive(AutoDebug, AutoStr, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub enum MusicState {
     /// Music file exists.
     Exists,
@@ -11,12 +14,33 @@ pub enum MusicState {
     /// Music file not exists.
     NotExists,
 
+    /// Music file exists but can not be decoded.
+    Corrupt,
+
     /// Unknown state or no checked.
     #[default]
     Unknown,
 }
 
-#[derive(AutoDebug, Clone)]
+/// Play statistics of a [`Music`], maintained by the player.
+#[derive(AutoDebug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MusicStats {
+    /// Count of times played to the end.
+    pub play_count: u32,
+
+    /// Count of times skipped before the end.
+    pub skip_count: u32,
+
+    /// When started playing last time, in milliseconds since unix epoch.
+    pub last_played_ms: Option<u64>,
+
+    /// When added to playlist, in milliseconds since unix epoch.
+    pub added_ms: u64,
+}
+
+#[derive(AutoDebug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Music {
     /// File path of the music file.
     pub file_path: String,
@@ -33,4 +57,34 @@ pub struct Music {
     ///
     /// Cached value in memory or database.
     pub metadata: Option<Metadata>,
+
+    /// Is an internet radio station.
+    ///
+    /// Stations never end, replayed when the stream dropped.
+    pub station: bool,
+
+    /// Play statistics.
+    #[serde(default)]
+    pub stats: MusicStats,
+
+    /// The range in audio file if is a track in cue sheet.
+    ///
+    /// [`Self::file_path`] is a virtual path then, the audio file is in [`CueTrack::file_path`].
+    pub cue: Option<CueTrack>,
+}
+
+impl From<&Music> for MusicModel {
+    fn from(value: &Music) -> Self {
+        Self {
+            file_path: value.file_path.clone(),
+            file_name: value.file_name.clone(),
+            state: value.state.to_string(),
+            metadata: value.metadata.as_ref().map(Into::into),
+            station: value.station,
+            play_count: value.stats.play_count,
+            skip_count: value.stats.skip_count,
+            last_played_ms: value.stats.last_played_ms,
+            added_ms: value.stats.added_ms,
+        }
+    }
 }
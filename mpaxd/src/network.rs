@@ -0,0 +1,826 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::{debug, error, info, warn};
+use racros::AutoDebug;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{HeaderMap, RANGE};
+use reqwest::StatusCode;
+
+/// Max times to reconnect in a row when the connection dropped.
+const MAX_RECONNECT: u32 = 5;
+
+/// Time to wait before reconnecting.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Timeout of establishing a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Interval of tcp keepalive, to find out dead connections while downloading.
+const TCP_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Size of each chunk read from the connection.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Bytes kept before current reading position, for seeking back.
+///
+/// Data further behind is dropped to save memory.
+const BUFFER_KEEP_BEHIND: u64 = 1024 * 1024;
+
+/// Max bytes downloaded ahead of current reading position.
+///
+/// Downloading pauses when reached, until more data is read.
+const BUFFER_MAX_AHEAD: u64 = 4 * 1024 * 1024;
+
+/// Header asking the server to interleave ICY metadata into the stream.
+const HEADER_ICY_METADATA: &str = "Icy-MetaData";
+
+/// Header of the count of audio bytes between two ICY metadata blocks.
+const HEADER_ICY_METAINT: &str = "icy-metaint";
+
+/// Header of the ICY station name.
+const HEADER_ICY_NAME: &str = "icy-name";
+
+/// Check whether `path` is a network url supported by [`HttpStream`].
+pub fn is_network_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Data downloaded so far, shared between [`HttpStream`] and its downloading thread.
+#[derive(AutoDebug, Default)]
+struct Buffer {
+    /// Downloaded bytes from [`Self::base`].
+    #[debug_value = "[u8]"]
+    data: Vec<u8>,
+
+    /// Position of the first byte in [`Self::data`].
+    base: u64,
+
+    /// Current reading position of [`HttpStream`].
+    position: u64,
+
+    /// Data at [`Self::position`] was dropped, download again from [`Self::base`].
+    ///
+    /// Only in streams that are not live.
+    restart: bool,
+
+    /// Total length of the stream, `None` if unknown.
+    total: Option<u64>,
+
+    /// Downloaded to the end of stream.
+    finished: bool,
+
+    /// Downloading failed and gave up.
+    error: Option<String>,
+
+    /// Latest stream title in ICY metadata.
+    title: Option<String>,
+}
+
+impl Buffer {
+    /// Position right after the last downloaded byte.
+    fn end(&self) -> u64 {
+        self.base + self.data.len() as u64
+    }
+
+    /// Drop data too far behind the reading position.
+    ///
+    /// Done in batch to avoid moving data too often.
+    fn evict(&mut self) {
+        let keep_from = self
+            .position
+            .saturating_sub(BUFFER_KEEP_BEHIND)
+            .min(self.end());
+        if keep_from >= self.base + BUFFER_KEEP_BEHIND {
+            let dropped = keep_from - self.base;
+            self.data
+                .drain(..usize::try_from(dropped).unwrap_or(usize::MAX));
+            self.base = keep_from;
+        }
+    }
+}
+
+/// State shared between [`HttpStream`] and its downloading thread.
+struct Shared {
+    buffer: Mutex<Buffer>,
+
+    /// Is a live stream, e.g. internet radio or any stream without a length.
+    ///
+    /// Live streams never end, reconnect from the current live position when dropped.
+    live: bool,
+
+    /// Notified every time [`Self::buffer`] changed.
+    changed: Condvar,
+
+    /// Set when [`HttpStream`] dropped, the downloading thread quits on it.
+    closed: AtomicBool,
+}
+
+impl Shared {
+    /// Block until data at `position` downloaded or nothing more to download.
+    ///
+    /// Return the buffer guard.
+    fn wait_for(&self, position: u64) -> io::Result<std::sync::MutexGuard<'_, Buffer>> {
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.end() <= position && !buffer.finished {
+            if let Some(e) = &buffer.error {
+                return Err(io::Error::new(io::ErrorKind::ConnectionAborted, e.clone()));
+            }
+            buffer = self.changed.wait(buffer).unwrap();
+        }
+        Ok(buffer)
+    }
+
+    /// Block until there is room to download more data, or the stream closed or restarted.
+    ///
+    /// Return the buffer guard.
+    fn wait_for_room(&self) -> std::sync::MutexGuard<'_, Buffer> {
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.end() >= buffer.position + BUFFER_MAX_AHEAD
+            && !buffer.restart
+            && !self.closed.load(Ordering::SeqCst)
+        {
+            buffer = self.changed.wait(buffer).unwrap();
+        }
+        buffer
+    }
+
+    /// Block until the stream restarted or closed.
+    ///
+    /// Return `false` if closed.
+    fn wait_for_restart(&self) -> bool {
+        let mut buffer = self.buffer.lock().unwrap();
+        while !buffer.restart && !self.closed.load(Ordering::SeqCst) {
+            buffer = self.changed.wait(buffer).unwrap();
+        }
+        buffer.restart
+    }
+}
+
+/// Audio data streamed over http.
+///
+/// Data is downloaded in a background thread and buffered in memory, reading blocks until the
+/// requested data arrives. When the connection dropped, reconnect and continue from where it
+/// stopped with a `Range` request.
+///
+/// Only [`BUFFER_MAX_AHEAD`] bytes are buffered ahead of the reading position and
+/// [`BUFFER_KEEP_BEHIND`] bytes behind it, so memory stays bounded in long or paused streams.
+///
+/// Seeking forward waits for the data to download. Seeking back to dropped data downloads
+/// again from there, except in live streams.
+///
+/// Shoutcast/Icecast streams are live streams, ICY metadata interleaved in them is stripped
+/// and can be read from [`StreamInfo`].
+#[derive(AutoDebug)]
+pub struct HttpStream {
+    /// Url of the stream.
+    url: String,
+
+    /// Station name, only in ICY streams.
+    name: Option<String>,
+
+    /// Current reading position.
+    position: u64,
+
+    #[debug_ignore]
+    shared: Arc<Shared>,
+}
+
+impl HttpStream {
+    /// Connect to `url` and start downloading in background.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to build the http client.
+    /// * When failed to connect to `url`, or the server replied an error.
+    pub fn open(url: &str) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            // Streams can be as long as they want, no timeout for the whole request.
+            .timeout(None)
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .build()
+            .context("failed to build http client")?;
+        // Connect in place to report errors of an unreachable url early.
+        let response = request(&client, url, 0)?;
+        let headers = response.headers();
+        let live = response.content_length().is_none()
+            || headers.keys().any(|x| x.as_str().starts_with("icy-"));
+        let name = headers
+            .get(HEADER_ICY_NAME)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty());
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(Buffer {
+                total: if live {
+                    None
+                } else {
+                    response.content_length()
+                },
+                ..Buffer::default()
+            }),
+            live,
+            changed: Condvar::new(),
+            closed: AtomicBool::new(false),
+        });
+
+        let shared2 = shared.clone();
+        let url2 = url.to_string();
+        thread::Builder::new()
+            .name("http-stream".to_string())
+            .spawn(move || download(&client, &url2, response, &shared2))
+            .context("failed to start downloading thread")?;
+        info!("start streaming {url}, live={live}, name={name:?}");
+
+        Ok(Self {
+            url: url.to_string(),
+            name,
+            position: 0,
+            shared,
+        })
+    }
+
+    /// Get the [`StreamInfo`] of this stream, still available after the stream dropped.
+    pub fn info(&self) -> StreamInfo {
+        StreamInfo {
+            name: self.name.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for HttpStream {
+    fn drop(&mut self) {
+        debug!("close http stream {}", self.url);
+        // Hold the lock so the downloading thread does not miss the notification.
+        let buffer = self.shared.buffer.lock().unwrap();
+        self.shared.closed.store(true, Ordering::SeqCst);
+        drop(buffer);
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Read for HttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut buffer = self.shared.buffer.lock().unwrap();
+        if self.position < buffer.base {
+            if self.shared.live {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "data already dropped in live stream",
+                ));
+            }
+            debug!("download {} again from {}", self.url, self.position);
+            buffer.data.clear();
+            buffer.base = self.position;
+            buffer.finished = false;
+            buffer.restart = true;
+        }
+        // Let the downloading thread know where we are, it may be waiting for room.
+        buffer.position = self.position;
+        drop(buffer);
+        self.shared.changed.notify_all();
+
+        let buffer = self.shared.wait_for(self.position)?;
+        let start = usize::try_from(self.position - buffer.base).unwrap_or(usize::MAX);
+        if start >= buffer.data.len() {
+            // Finished.
+            return Ok(0);
+        }
+        let count = buf.len().min(buffer.data.len() - start);
+        buf[..count].copy_from_slice(&buffer.data[start..start + count]);
+        self.position += count as u64;
+        drop(buffer);
+        Ok(count)
+    }
+}
+
+impl Seek for HttpStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::Current(v) => self.position.checked_add_signed(v),
+            SeekFrom::End(v) => {
+                let buffer = self.shared.buffer.lock().unwrap();
+                let total = if buffer.finished {
+                    Some(buffer.end())
+                } else {
+                    buffer.total
+                };
+                let Some(total) = total else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "can not seek from end: length of stream unknown",
+                    ));
+                };
+                total.checked_add_signed(v)
+            }
+        };
+        let Some(target) = target else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative position",
+            ));
+        };
+        self.position = target;
+        Ok(target)
+    }
+}
+
+/// Information of a [`HttpStream`] updated while streaming.
+#[derive(AutoDebug, Clone)]
+pub struct StreamInfo {
+    /// Station name, only in ICY streams.
+    name: Option<String>,
+
+    #[debug_ignore]
+    shared: Arc<Shared>,
+}
+
+impl StreamInfo {
+    /// Get the station name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Is a live stream that never ends.
+    pub fn live(&self) -> bool {
+        self.shared.live
+    }
+
+    /// Get the latest stream title in ICY metadata.
+    pub fn title(&self) -> Option<String> {
+        self.shared.buffer.lock().unwrap().title.clone()
+    }
+}
+
+/// State of [`IcyParser`].
+#[derive(Clone, Copy)]
+enum IcyState {
+    /// Reading audio data, count of bytes left before next metadata block.
+    Audio(usize),
+
+    /// Reading the length byte of next metadata block.
+    MetadataLength,
+
+    /// Reading the metadata block, count of bytes left.
+    Metadata(usize),
+}
+
+/// Strip ICY metadata interleaved in the audio data of Shoutcast/Icecast streams.
+///
+/// The stream is `icy-metaint` bytes of audio, followed by a length byte `n` and `n * 16` bytes
+/// of metadata, repeatedly.
+struct IcyParser {
+    /// Count of audio bytes between metadata blocks.
+    interval: usize,
+
+    state: IcyState,
+
+    /// Metadata block being read.
+    metadata: Vec<u8>,
+}
+
+impl IcyParser {
+    /// Construct from response headers, `None` if the server does not send metadata.
+    fn new(headers: &HeaderMap) -> Option<Self> {
+        let interval = headers
+            .get(HEADER_ICY_METAINT)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|x| *x > 0)?;
+        Some(Self {
+            interval,
+            state: IcyState::Audio(interval),
+            metadata: vec![],
+        })
+    }
+
+    /// Feed `data` received, append audio data in it to `audio`.
+    ///
+    /// Return the stream title if a metadata block with it finished in `data`, empty title
+    /// means no title.
+    fn feed(&mut self, mut data: &[u8], audio: &mut Vec<u8>) -> Option<String> {
+        let mut title = None;
+        while !data.is_empty() {
+            match self.state {
+                IcyState::Audio(left) => {
+                    let count = left.min(data.len());
+                    audio.extend_from_slice(&data[..count]);
+                    data = &data[count..];
+                    self.state = if count == left {
+                        IcyState::MetadataLength
+                    } else {
+                        IcyState::Audio(left - count)
+                    };
+                }
+                IcyState::MetadataLength => {
+                    let length = usize::from(data[0]) * 16;
+                    data = &data[1..];
+                    self.metadata.clear();
+                    self.state = if length == 0 {
+                        IcyState::Audio(self.interval)
+                    } else {
+                        IcyState::Metadata(length)
+                    };
+                }
+                IcyState::Metadata(left) => {
+                    let count = left.min(data.len());
+                    self.metadata.extend_from_slice(&data[..count]);
+                    data = &data[count..];
+                    if count == left {
+                        if let Some(v) = parse_stream_title(&self.metadata) {
+                            title = Some(v);
+                        }
+                        self.state = IcyState::Audio(self.interval);
+                    } else {
+                        self.state = IcyState::Metadata(left - count);
+                    }
+                }
+            }
+        }
+        title
+    }
+}
+
+/// Parse `StreamTitle` in ICY metadata like `StreamTitle='Artist - Title';StreamUrl='';`.
+///
+/// Return `None` if no `StreamTitle` in `metadata`.
+fn parse_stream_title(metadata: &[u8]) -> Option<String> {
+    const KEY: &str = "StreamTitle='";
+    let metadata = String::from_utf8_lossy(metadata);
+    let metadata = metadata.trim_end_matches('\0');
+    let value = &metadata[metadata.find(KEY)? + KEY.len()..];
+    // Titles may contain quotes, take the one followed by the field separator as the end.
+    let end = value
+        .find("';")
+        .or_else(|| value.rfind('\''))
+        .unwrap_or(value.len());
+    Some(value[..end].trim().to_string())
+}
+
+/// Send a GET request to `url`, starting from `offset`.
+fn request(client: &Client, url: &str, offset: u64) -> Result<Response> {
+    let mut builder = client.get(url).header(HEADER_ICY_METADATA, "1");
+    if offset > 0 {
+        builder = builder.header(RANGE, format!("bytes={offset}-"));
+    }
+    let response = builder
+        .send()
+        .with_context(|| format!("failed to connect to {url}"))?;
+    if !response.status().is_success() {
+        bail!("server replied error: {url}, code={}", response.status());
+    }
+    Ok(response)
+}
+
+/// Download the stream into `shared` buffer, reconnect when the connection dropped.
+///
+/// Give up after failed to reconnect [`MAX_RECONNECT`] times in a row.
+fn download(client: &Client, url: &str, response: Response, shared: &Shared) {
+    let mut response = Some(response);
+    let mut retry = 0;
+    loop {
+        let offset = shared.buffer.lock().unwrap().end();
+        // Live streams can not be resumed, continue from the current live position instead.
+        let current = response.take().map_or_else(
+            || request(client, url, if shared.live { 0 } else { offset }),
+            Ok,
+        );
+        let result = current.and_then(|x| {
+            // Server ignored the range request and sent all from the start.
+            let skip = if !shared.live && offset > 0 && x.status() != StatusCode::PARTIAL_CONTENT {
+                offset
+            } else {
+                0
+            };
+            read_response(x, skip, shared)
+        });
+        let reason = match result {
+            Ok(ResponseEnd::Closed) => {
+                debug!("stop downloading {url}: stream closed");
+                return;
+            }
+            Ok(ResponseEnd::Restart) => {
+                shared.buffer.lock().unwrap().restart = false;
+                retry = 0;
+                continue;
+            }
+            Ok(ResponseEnd::Eof) => {
+                let mut buffer = shared.buffer.lock().unwrap();
+                let length = buffer.end();
+                if !shared.live && buffer.total.is_none_or(|x| length >= x) {
+                    info!("finished downloading {url}: {length} bytes");
+                    buffer.finished = true;
+                    drop(buffer);
+                    shared.changed.notify_all();
+                    // Data may be dropped and downloaded again when seeking back.
+                    if !shared.wait_for_restart() {
+                        return;
+                    }
+                    shared.buffer.lock().unwrap().restart = false;
+                    retry = 0;
+                    continue;
+                }
+                format!("connection ended at {length} bytes")
+            }
+            Err(e) => format!("{e:#}"),
+        };
+
+        if shared.buffer.lock().unwrap().end() > offset {
+            // Made some progress, not failing in a row.
+            retry = 0;
+        }
+        retry += 1;
+        if retry > MAX_RECONNECT {
+            error!("failed to download {url}: {reason}");
+            shared.buffer.lock().unwrap().error = Some(reason);
+            shared.changed.notify_all();
+            return;
+        }
+        warn!("connection to {url} dropped, reconnect ({retry}/{MAX_RECONNECT}): {reason}");
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Why [`read_response`] stopped.
+enum ResponseEnd {
+    /// Reached the end of response.
+    Eof,
+
+    /// The stream closed, stop downloading.
+    Closed,
+
+    /// The stream restarted at another position, download again from there.
+    Restart,
+}
+
+/// Read all data in `response` into `shared` buffer, drop the first `skip` bytes.
+///
+/// ICY metadata in the response, if any, is stripped and saved as the stream title.
+fn read_response(mut response: Response, mut skip: u64, shared: &Shared) -> Result<ResponseEnd> {
+    let mut icy = IcyParser::new(response.headers());
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    loop {
+        // Stop reading the connection when buffered enough, the server waits for us.
+        let buffer = shared.wait_for_room();
+        if shared.closed.load(Ordering::SeqCst) {
+            return Ok(ResponseEnd::Closed);
+        }
+        if buffer.restart {
+            return Ok(ResponseEnd::Restart);
+        }
+        drop(buffer);
+        let count = response.read(&mut chunk)?;
+        if count == 0 {
+            return Ok(ResponseEnd::Eof);
+        }
+        let mut data = &chunk[..count];
+        if skip > 0 {
+            let n = usize::try_from(skip).unwrap_or(usize::MAX).min(data.len());
+            data = &data[n..];
+            skip -= n as u64;
+        }
+        let mut buffer = shared.buffer.lock().unwrap();
+        if buffer.restart {
+            // Data received is not at the restarted position.
+            return Ok(ResponseEnd::Restart);
+        }
+        buffer.evict();
+        match icy.as_mut() {
+            Some(parser) => {
+                let title = parser
+                    .feed(data, &mut buffer.data)
+                    .map(|x| Some(x).filter(|x| !x.is_empty()));
+                if let Some(title) = title.filter(|x| *x != buffer.title) {
+                    info!("stream title changed: {title:?}");
+                    buffer.title = title;
+                }
+            }
+            None => buffer.data.extend_from_slice(data),
+        }
+        drop(buffer);
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{channel, Receiver};
+
+    use super::*;
+
+    /// How [`serve`] replies a request.
+    enum Reply {
+        /// Reply the requested range, but close the connection after sending this many bytes.
+        Partial(usize),
+
+        /// Reply the requested range.
+        Full,
+    }
+
+    /// Serve `data` on a local port, replying requests with `replies` in order.
+    ///
+    /// Return the url of the listener and a receiver of the `Range` header of each request.
+    fn serve(data: Vec<u8>, replies: Vec<Reply>) -> (String, Receiver<Option<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for reply in replies {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                let mut range = None;
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.trim_end().split_once(": ") {
+                        if name.eq_ignore_ascii_case("range") {
+                            range = Some(value.to_string());
+                        }
+                    }
+                }
+                let start = range
+                    .as_deref()
+                    .and_then(|x| x.strip_prefix("bytes="))
+                    .and_then(|x| x.strip_suffix('-'))
+                    .map_or(0, |x| x.parse::<usize>().unwrap());
+                let status = if start > 0 {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                let body = &data[start..];
+                let stream = reader.get_mut();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                let count = match reply {
+                    Reply::Partial(v) => v.min(body.len()),
+                    Reply::Full => body.len(),
+                };
+                // The reader may close early, ignore errors.
+                let _ = stream.write_all(&body[..count]);
+                let _ = tx.send(range);
+            }
+        });
+        (url, rx)
+    }
+
+    /// Generate `length` bytes of data that differ at each position.
+    fn sample_data(length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|x| u8::try_from(x % 251).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_reconnect_with_range() {
+        let data = sample_data(100_000);
+        let (url, rx) = serve(data.clone(), vec![Reply::Partial(30_000), Reply::Full]);
+        let mut stream = HttpStream::open(&url).unwrap();
+        assert!(!stream.info().live());
+        let mut received = vec![];
+        stream.read_to_end(&mut received).unwrap();
+        assert_eq!(received, data);
+        assert_eq!(rx.recv().unwrap(), None);
+        assert_eq!(rx.recv().unwrap().as_deref(), Some("bytes=30000-"));
+    }
+
+    #[test]
+    fn test_buffer_bounded() {
+        let length = usize::try_from(BUFFER_MAX_AHEAD * 4).unwrap();
+        let data = sample_data(length);
+        let (url, _rx) = serve(data.clone(), vec![Reply::Full, Reply::Full]);
+        let mut stream = HttpStream::open(&url).unwrap();
+        // Not reading, downloading pauses when buffered enough.
+        thread::sleep(Duration::from_millis(500));
+        let buffered = stream.shared.buffer.lock().unwrap().end();
+        assert!(buffered <= BUFFER_MAX_AHEAD + CHUNK_SIZE as u64);
+
+        let mut received = vec![];
+        stream.read_to_end(&mut received).unwrap();
+        assert_eq!(received, data);
+        let buffered = stream.shared.buffer.lock().unwrap().data.len() as u64;
+        assert!(buffered <= BUFFER_MAX_AHEAD + BUFFER_KEEP_BEHIND * 2 + CHUNK_SIZE as u64);
+
+        // Seeking back to dropped data downloads again from there.
+        stream.seek(SeekFrom::Start(10)).unwrap();
+        let mut head = [0u8; 100];
+        stream.read_exact(&mut head).unwrap();
+        assert_eq!(head, data[10..110]);
+    }
+
+    #[test]
+    fn test_no_length_is_live() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\ndata");
+        });
+        let stream = HttpStream::open(&url).unwrap();
+        assert!(stream.info().live());
+    }
+
+    /// Build an ICY metadata block of `metadata` padded with NUL, prefixed with the length byte.
+    fn icy_block(metadata: &str) -> Vec<u8> {
+        let length = metadata.len().div_ceil(16);
+        let mut block = vec![u8::try_from(length).unwrap()];
+        block.extend_from_slice(metadata.as_bytes());
+        block.resize(1 + length * 16, 0);
+        block
+    }
+
+    /// Build an [`IcyParser`] with metadata every `interval` bytes.
+    fn icy_parser(interval: usize) -> IcyParser {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_ICY_METAINT, interval.to_string().parse().unwrap());
+        IcyParser::new(&headers).unwrap()
+    }
+
+    #[test]
+    fn test_parse_stream_title() {
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Artist - Title';StreamUrl='';").as_deref(),
+            Some("Artist - Title")
+        );
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Artist - Title';\0\0\0\0\0").as_deref(),
+            Some("Artist - Title")
+        );
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='Rock 'n' Roll';StreamUrl='x';").as_deref(),
+            Some("Rock 'n' Roll")
+        );
+        // Field separator missing.
+        assert_eq!(
+            parse_stream_title(b"StreamTitle='It's Me'\0\0").as_deref(),
+            Some("It's Me")
+        );
+        assert_eq!(parse_stream_title(b"StreamTitle='';").as_deref(), Some(""));
+        assert_eq!(parse_stream_title(b"StreamUrl='x';\0\0"), None);
+        assert_eq!(parse_stream_title(b""), None);
+    }
+
+    #[test]
+    fn test_icy_strip_metadata() {
+        let mut data = b"0123456789".to_vec();
+        data.extend(icy_block("StreamTitle='A - B';"));
+        data.extend_from_slice(b"abcdefghij");
+        // Empty metadata block.
+        data.push(0);
+        data.extend_from_slice(b"ABCDEFGHIJ");
+        data.extend(icy_block("StreamTitle='C - D';StreamUrl='';"));
+        data.extend_from_slice(b"xyz");
+
+        // Whole data at once, the latest title wins.
+        let mut audio = vec![];
+        let title = icy_parser(10).feed(&data, &mut audio);
+        assert_eq!(audio, b"0123456789abcdefghijABCDEFGHIJxyz");
+        assert_eq!(title.as_deref(), Some("C - D"));
+
+        // Data split at every position, including inside metadata blocks and length bytes.
+        let mut parser = icy_parser(10);
+        let mut audio = vec![];
+        let mut titles = vec![];
+        for byte in &data {
+            titles.extend(parser.feed(std::slice::from_ref(byte), &mut audio));
+        }
+        assert_eq!(audio, b"0123456789abcdefghijABCDEFGHIJxyz");
+        assert_eq!(titles, ["A - B", "C - D"]);
+    }
+
+    #[test]
+    fn test_icy_parser_needs_metaint() {
+        assert!(IcyParser::new(&HeaderMap::new()).is_none());
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_ICY_METAINT, "0".parse().unwrap());
+        assert!(IcyParser::new(&headers).is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{Context, Result};
+use libmpax::dto::{AnalysisModel, ChannelLevelModel, SpectrumBandModel};
+use log::info;
+use racros::AutoDebug;
+use rust_i18n::t;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use tokio::sync::broadcast;
+
+use crate::config::AnalyzerConfig;
+use crate::output::{Tap, OUTPUT_CHANNELS, OUTPUT_SAMPLE_RATE, TAP_QUEUE_LEN};
+
+/// Max count of analyses queued for a listener, a listener falling behind skips the oldest ones.
+const ANALYSIS_QUEUE_LEN: usize = 16;
+
+/// Lowest frequency in spectrum.
+const SPECTRUM_MIN_FREQUENCY: f32 = 20.0;
+
+/// Highest frequency in spectrum, capped at the nyquist frequency.
+const SPECTRUM_MAX_FREQUENCY: f32 = 20000.0;
+
+/// Level reported for silence in spectrum.
+const SPECTRUM_FLOOR_DB: f32 = -120.0;
+
+/// Analyze levels and spectrum of mixed output and broadcast to all listeners.
+///
+/// Nothing is computed when no listener.
+#[derive(AutoDebug, Clone)]
+pub struct Analyzer {
+    config: AnalyzerConfig,
+
+    #[debug_ignore]
+    tx: broadcast::Sender<AnalysisModel>,
+}
+
+impl Analyzer {
+    /// Construct with `config`, nothing is broadcast until the analyzer started.
+    pub fn new(config: AnalyzerConfig) -> Self {
+        let (tx, _) = broadcast::channel(ANALYSIS_QUEUE_LEN);
+        Self { config, tx }
+    }
+
+    /// Add a listener.
+    pub fn subscribe(&self) -> broadcast::Receiver<AnalysisModel> {
+        let rx = self.tx.subscribe();
+        info!(
+            "analysis listener joined, {} in total",
+            self.tx.receiver_count()
+        );
+        rx
+    }
+
+    /// Start a thread analyzing the mixed output and broadcasting the result to listeners.
+    ///
+    /// Return the [`Tap`] to feed mixed output.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to start the thread.
+    pub(super) fn spawn(&self) -> Result<Tap> {
+        let (tap, rx) = sync_channel(TAP_QUEUE_LEN);
+        let analyzer = self.clone();
+        thread::Builder::new()
+            .name("analyzer".to_string())
+            .spawn(move || analyzer.run(&rx))
+            .context(t!("player.failedToInit"))?;
+        Ok(tap)
+    }
+
+    /// Analyze mixed output received from `rx` at configured rate, until the mixed output
+    /// closed.
+    fn run(&self, rx: &Receiver<Vec<f32>>) {
+        let channels = usize::from(OUTPUT_CHANNELS);
+        let interval = (OUTPUT_SAMPLE_RATE / self.config.rate).max(1) as usize;
+        let mut spectrum = Spectrum::new(self.config.fft_size, self.config.bands);
+        let mut levels = vec![Level::default(); channels];
+        let mut frames = 0;
+        while let Ok(chunk) = rx.recv() {
+            for frame in chunk.chunks_exact(channels) {
+                for (level, sample) in levels.iter_mut().zip(frame) {
+                    level.add(*sample);
+                }
+                #[allow(clippy::cast_precision_loss)]
+                spectrum.push(frame.iter().sum::<f32>() / channels as f32);
+                frames += 1;
+                if frames < interval {
+                    continue;
+                }
+                if self.tx.receiver_count() > 0 {
+                    let analysis = AnalysisModel {
+                        channels: levels.iter().map(Level::model).collect(),
+                        spectrum: spectrum.compute(),
+                    };
+                    // Error only means no listener now.
+                    let _ = self.tx.send(analysis);
+                }
+                levels.fill(Level::default());
+                frames = 0;
+            }
+        }
+        info!("analyzer stopped: output closed");
+    }
+}
+
+/// Peak and rms level of a channel being measured.
+#[derive(Clone, Default)]
+struct Level {
+    peak: f32,
+    square_sum: f32,
+    count: u32,
+}
+
+impl Level {
+    fn add(&mut self, sample: f32) {
+        self.peak = self.peak.max(sample.abs());
+        self.square_sum += sample * sample;
+        self.count += 1;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn model(&self) -> ChannelLevelModel {
+        let rms = if self.count == 0 {
+            0.0
+        } else {
+            (self.square_sum / self.count as f32).sqrt()
+        };
+        ChannelLevelModel {
+            peak: self.peak.min(1.0),
+            rms: rms.min(1.0),
+        }
+    }
+}
+
+/// Spectrum of the latest samples, in logarithmically spaced bands.
+struct Spectrum {
+    fft: Arc<dyn Fft<f32>>,
+
+    /// Latest samples, at most the fft size.
+    samples: VecDeque<f32>,
+
+    /// Hann window.
+    window: Vec<f32>,
+
+    /// Sum of [`Self::window`], to normalize magnitudes.
+    window_sum: f32,
+
+    /// Frequency range of each band in fft bins, inclusive.
+    bands: Vec<(usize, usize)>,
+
+    /// Center frequency of each band.
+    frequencies: Vec<f32>,
+
+    buffer: Vec<Complex<f32>>,
+}
+
+impl Spectrum {
+    #[allow(clippy::cast_precision_loss)]
+    fn new(fft_size: usize, band_count: usize) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(fft_size);
+        let window: Vec<f32> = (0..fft_size)
+            .map(|i| (1.0 - (2.0 * PI * i as f32 / fft_size as f32).cos()) / 2.0)
+            .collect();
+        let window_sum = window.iter().sum();
+
+        let bin_width = OUTPUT_SAMPLE_RATE as f32 / fft_size as f32;
+        let max_frequency = SPECTRUM_MAX_FREQUENCY.min(OUTPUT_SAMPLE_RATE as f32 / 2.0);
+        let ratio = max_frequency / SPECTRUM_MIN_FREQUENCY;
+        let edge = |i: usize| SPECTRUM_MIN_FREQUENCY * ratio.powf(i as f32 / band_count as f32);
+        let last_bin = fft_size / 2;
+        let to_bin = |frequency: f32| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let bin = (frequency / bin_width).round() as usize;
+            bin.clamp(1, last_bin)
+        };
+        let (bands, frequencies) = (0..band_count)
+            .map(|i| {
+                let (low, high) = (edge(i), edge(i + 1));
+                // Narrow bands at low frequency may fall between two bins, take the nearest one.
+                let (low_bin, high_bin) = (to_bin(low), to_bin(high));
+                ((low_bin, high_bin.max(low_bin)), (low * high).sqrt())
+            })
+            .unzip();
+
+        Self {
+            fft,
+            samples: VecDeque::with_capacity(fft_size),
+            window,
+            window_sum,
+            bands,
+            frequencies,
+            buffer: Vec::with_capacity(fft_size),
+        }
+    }
+
+    /// Add a mono sample, drop the oldest one if full.
+    fn push(&mut self, sample: f32) {
+        if self.samples.len() == self.window.len() {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Compute levels of each band, missing samples are taken as silence.
+    fn compute(&mut self) -> Vec<SpectrumBandModel> {
+        let padding = self.window.len() - self.samples.len();
+        self.buffer.clear();
+        self.buffer.extend(
+            std::iter::repeat_n(0.0, padding)
+                .chain(self.samples.iter().copied())
+                .zip(&self.window)
+                .map(|(sample, weight)| Complex::new(sample * weight, 0.0)),
+        );
+        self.fft.process(&mut self.buffer);
+        self.bands
+            .iter()
+            .zip(&self.frequencies)
+            .map(|(&(low, high), &frequency)| {
+                // Amplitude of a full scale sine wave is normalized to 1.0.
+                let magnitude = self.buffer[low..=high]
+                    .iter()
+                    .map(|x| x.norm() * 2.0 / self.window_sum)
+                    .fold(0.0, f32::max);
+                let level = if magnitude > 0.0 {
+                    (20.0 * magnitude.log10()).max(SPECTRUM_FLOOR_DB)
+                } else {
+                    SPECTRUM_FLOOR_DB
+                };
+                SpectrumBandModel { frequency, level }
+            })
+            .collect()
+    }
+}
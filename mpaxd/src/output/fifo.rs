@@ -0,0 +1,117 @@
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+
+use anyhow::{bail, Context, Result};
+use log::{error, info};
+use rodio::cpal::Sample;
+use rodio::source::UniformSourceIterator;
+use rust_i18n::t;
+
+use crate::config::{FifoConfig, PcmFormat};
+use crate::output::{Tap, TapSource, TAP_QUEUE_LEN};
+
+/// Frames written to the fifo in each write.
+const FIFO_WRITE_FRAMES: usize = 512;
+
+/// Start a thread writing raw pcm of the mixed output to the fifo in `config`.
+///
+/// The fifo is created if not exists. Writing starts when a reader opens the fifo, and waits
+/// for the next reader after it closed. Audio is dropped when no reader.
+///
+/// Return the [`Tap`] to feed mixed output.
+///
+/// # Errors
+///
+/// * When failed to create the fifo.
+/// * When failed to start the thread.
+pub fn spawn_fifo(config: FifoConfig) -> Result<Tap> {
+    create_fifo(&config.path)?;
+    let (tx, rx) = sync_channel(TAP_QUEUE_LEN);
+    thread::Builder::new()
+        .name("fifo-output".to_string())
+        .spawn(move || {
+            if let Err(e) = run_fifo(&config, rx) {
+                error!("fifo output stopped with error: {e:#}");
+            }
+        })
+        .context(t!("player.failedToInit"))?;
+    Ok(tx)
+}
+
+/// Create a fifo at `path` if not exists.
+fn create_fifo(path: &Path) -> Result<()> {
+    match path.metadata() {
+        Ok(v) if v.file_type().is_fifo() => return Ok(()),
+        Ok(_) => bail!(t!("output.notAFifo", path = path.display())),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).context(t!("output.failedToCreateFifo", path = path.display()));
+        }
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .context(t!("output.failedToCreateFifo", path = path.display()))?;
+    // SAFETY: `c_path` is a valid nul terminated string.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .context(t!("output.failedToCreateFifo", path = path.display()));
+    }
+    info!("created fifo {}", path.display());
+    Ok(())
+}
+
+/// Convert the mixed output received from `rx` to the format in `config` and write it to the
+/// fifo, until the mixed output closed.
+fn run_fifo(config: &FifoConfig, rx: Receiver<Vec<f32>>) -> Result<()> {
+    let mut samples: UniformSourceIterator<TapSource, f32> =
+        UniformSourceIterator::new(TapSource::new(rx), config.channels, config.sample_rate);
+    let chunk_samples = FIFO_WRITE_FRAMES * usize::from(config.channels);
+    let mut buffer = Vec::with_capacity(chunk_samples * config.format.sample_size());
+    loop {
+        // Blocks until a reader opens the fifo.
+        let mut fifo = open_fifo(&config.path)?;
+        info!("fifo {} opened by reader", config.path.display());
+        loop {
+            buffer.clear();
+            for _ in 0..chunk_samples {
+                let Some(sample) = samples.next() else {
+                    return Ok(());
+                };
+                encode_sample(config.format, sample, &mut buffer);
+            }
+            match fifo.write_all(&buffer) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    info!("fifo {} closed by reader", config.path.display());
+                    break;
+                }
+                Err(e) => {
+                    return Err(e)
+                        .context(t!("output.failedToWriteFifo", path = config.path.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Open the fifo at `path` for writing.
+fn open_fifo(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .context(t!("output.failedToOpenFifo", path = path.display()))
+}
+
+/// Append `sample` in `format` to `buffer`.
+fn encode_sample(format: PcmFormat, sample: f32, buffer: &mut Vec<u8>) {
+    match format {
+        PcmFormat::S16 => buffer.extend_from_slice(&sample.to_sample::<i16>().to_le_bytes()),
+        PcmFormat::S32 => buffer.extend_from_slice(&sample.to_sample::<i32>().to_le_bytes()),
+        PcmFormat::F32 => buffer.extend_from_slice(&sample.to_le_bytes()),
+    }
+}
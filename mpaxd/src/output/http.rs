@@ -0,0 +1,122 @@
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use log::info;
+use racros::AutoDebug;
+use rodio::cpal::Sample;
+use rodio::source::UniformSourceIterator;
+use rust_i18n::t;
+use tokio::sync::broadcast;
+
+use crate::config::HttpOutputConfig;
+use crate::output::{Tap, TapSource, TAP_QUEUE_LEN};
+
+/// Frames in each chunk sent to listeners.
+const HTTP_CHUNK_FRAMES: usize = 2048;
+
+/// Max count of chunks queued for a listener, a listener falling behind skips the oldest ones.
+const HTTP_QUEUE_LEN: usize = 64;
+
+/// Send silence to listeners when no mixed output received in this time.
+const HTTP_IDLE_SILENCE: Duration = Duration::from_millis(500);
+
+/// Mixed output encoded as an endless wav stream and broadcast to all http listeners.
+///
+/// Keeps streaming silence between tracks, while stopped and before the first play, listeners
+/// never see the end of stream unless the daemon exits.
+#[derive(AutoDebug, Clone)]
+pub struct HttpBroadcast {
+    config: HttpOutputConfig,
+
+    #[debug_ignore]
+    tx: broadcast::Sender<Bytes>,
+}
+
+impl HttpBroadcast {
+    /// Construct with `config`, nothing is broadcast until the encoder started.
+    pub fn new(config: HttpOutputConfig) -> Self {
+        let (tx, _) = broadcast::channel(HTTP_QUEUE_LEN);
+        Self { config, tx }
+    }
+
+    /// Add a listener.
+    ///
+    /// Return the wav header to send first, and the receiver of following audio data.
+    pub fn subscribe(&self) -> (Bytes, broadcast::Receiver<Bytes>) {
+        let rx = self.tx.subscribe();
+        info!(
+            "http stream listener joined, {} in total",
+            self.tx.receiver_count()
+        );
+        (self.wav_header(), rx)
+    }
+
+    /// Start a thread encoding the mixed output and broadcasting it to listeners.
+    ///
+    /// Return the [`Tap`] to feed mixed output.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to start the thread.
+    pub(super) fn spawn_encoder(&self) -> Result<Tap> {
+        let (tap, rx) = sync_channel(TAP_QUEUE_LEN);
+        let broadcast = self.clone();
+        thread::Builder::new()
+            .name("http-output".to_string())
+            .spawn(move || broadcast.run_encoder(rx))
+            .context(t!("player.failedToInit"))?;
+        Ok(tap)
+    }
+
+    /// Encode mixed output received from `rx` into signed 16 bit little endian pcm and send to
+    /// all listeners, until the mixed output closed.
+    fn run_encoder(&self, rx: Receiver<Vec<f32>>) {
+        let mut samples: UniformSourceIterator<TapSource, f32> = UniformSourceIterator::new(
+            TapSource::with_idle_silence(rx, HTTP_IDLE_SILENCE),
+            self.config.channels,
+            self.config.sample_rate,
+        );
+        let chunk_samples = HTTP_CHUNK_FRAMES * usize::from(self.config.channels);
+        loop {
+            let mut chunk = Vec::with_capacity(chunk_samples * 2);
+            for _ in 0..chunk_samples {
+                let Some(sample) = samples.next() else {
+                    info!("http stream encoder stopped: output closed");
+                    return;
+                };
+                chunk.extend_from_slice(&sample.to_sample::<i16>().to_le_bytes());
+            }
+            // Error only means no listener now.
+            let _ = self.tx.send(Bytes::from(chunk));
+        }
+    }
+
+    /// Build the header of an endless wav stream in current format.
+    ///
+    /// Lengths in header are set to max value as the stream never ends.
+    fn wav_header(&self) -> Bytes {
+        let channels = self.config.channels;
+        let sample_rate = self.config.sample_rate;
+        let block_align = channels * 2;
+        let byte_rate = sample_rate * u32::from(block_align);
+        let mut header = Vec::with_capacity(44);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        // PCM.
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&byte_rate.to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        // Bits per sample.
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&u32::MAX.to_le_bytes());
+        Bytes::from(header)
+    }
+}
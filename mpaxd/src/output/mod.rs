@@ -0,0 +1,433 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use log::{error, info};
+use racros::AutoDebug;
+use rodio::dynamic_mixer::{self, DynamicMixer, DynamicMixerController};
+use rodio::{OutputStream, Sink, Source};
+use rust_i18n::t;
+
+use crate::config::{OutputBackend, OutputConfig};
+pub use crate::output::analyzer::Analyzer;
+use crate::output::fifo::spawn_fifo;
+pub use crate::output::http::HttpBroadcast;
+
+mod analyzer;
+mod fifo;
+mod http;
+
+/// Channel count of mixed output.
+const OUTPUT_CHANNELS: u16 = 2;
+
+/// Sample rate of mixed output.
+const OUTPUT_SAMPLE_RATE: u32 = 44100;
+
+/// Frames produced in each step by headless backends, 10ms.
+const HEADLESS_STEP_FRAMES: u32 = OUTPUT_SAMPLE_RATE / 100;
+
+/// Interval to update the header of wav file, keeps the file valid if the daemon killed.
+const WAV_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Samples in each chunk sent to [`Tap`]s, 10ms.
+const TAP_CHUNK_SAMPLES: usize = (OUTPUT_SAMPLE_RATE / 100) as usize * OUTPUT_CHANNELS as usize;
+
+/// Max count of chunks queued in a [`Tap`], chunks are dropped when it's full.
+const TAP_QUEUE_LEN: usize = 16;
+
+/// Receiver of the mixed output in parallel to the backend.
+///
+/// Receives chunks of interleaved samples in [`OUTPUT_CHANNELS`] and [`OUTPUT_SAMPLE_RATE`].
+type Tap = SyncSender<Vec<f32>>;
+
+/// Outputs served on the http server, in parallel to the backend.
+#[derive(AutoDebug, Clone, Default)]
+pub struct HttpOutputs {
+    /// Http stream, disabled if `None`.
+    pub stream: Option<HttpBroadcast>,
+
+    /// Levels and spectrum analyzer, disabled if `None`.
+    pub analyzer: Option<Analyzer>,
+}
+
+/// Mixed audio of all [Sink]s created by [Output].
+///
+/// Never ends, produces silence when nothing playing.
+struct MixedSource {
+    mixer: DynamicMixer<f32>,
+
+    /// Also send the mixed audio to these taps.
+    taps: Vec<Tap>,
+
+    /// Samples not sent to [`Self::taps`] yet.
+    chunk: Vec<f32>,
+}
+
+impl MixedSource {
+    /// Send current chunk to all taps.
+    ///
+    /// A slow tap misses the chunk instead of blocking the output, closed ones are removed.
+    fn send_chunk(&mut self) {
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(TAP_CHUNK_SAMPLES));
+        self.taps.retain(|x| match x.try_send(chunk.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+impl Iterator for MixedSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.mixer.next().unwrap_or(0.0);
+        if !self.taps.is_empty() {
+            self.chunk.push(sample);
+            if self.chunk.len() >= TAP_CHUNK_SAMPLES {
+                self.send_chunk();
+            }
+        }
+        Some(sample)
+    }
+}
+
+impl Source for MixedSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        OUTPUT_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        OUTPUT_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Mixed output received by a [`Tap`], as a [Source].
+///
+/// Ends when the [`Output`] closed.
+struct TapSource {
+    rx: Receiver<Vec<f32>>,
+
+    /// Produce this long silence when nothing received in it, `None` to wait forever.
+    idle: Option<Duration>,
+
+    /// Current chunk.
+    chunk: std::vec::IntoIter<f32>,
+}
+
+impl TapSource {
+    fn new(rx: Receiver<Vec<f32>>) -> Self {
+        Self {
+            rx,
+            idle: None,
+            chunk: Vec::new().into_iter(),
+        }
+    }
+
+    /// Construct a source that never stalls, producing silence while nothing sent to the tap,
+    /// e.g. before the [`Output`] opened.
+    ///
+    /// `idle` must be longer than the intervals the backend consumes audio in, or silence is
+    /// inserted into playing audio.
+    fn with_idle_silence(rx: Receiver<Vec<f32>>, idle: Duration) -> Self {
+        Self {
+            idle: Some(idle),
+            ..Self::new(rx)
+        }
+    }
+
+    /// Receive the next chunk, `None` if the [`Output`] closed.
+    fn recv(&self) -> Option<Vec<f32>> {
+        let Some(idle) = self.idle else {
+            return self.rx.recv().ok();
+        };
+        match self.rx.recv_timeout(idle) {
+            Ok(v) => Some(v),
+            Err(RecvTimeoutError::Timeout) => {
+                let frames = idle.as_millis() * u128::from(OUTPUT_SAMPLE_RATE) / 1000;
+                let samples =
+                    usize::try_from(frames).unwrap_or(usize::MAX) * usize::from(OUTPUT_CHANNELS);
+                Some(vec![0.0; samples])
+            }
+            Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+impl Iterator for TapSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(v) = self.chunk.next() {
+                return Some(v);
+            }
+            self.chunk = self.recv()?.into_iter();
+        }
+    }
+}
+
+impl Source for TapSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        OUTPUT_CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        OUTPUT_SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Opened backend of [Output].
+enum Backend {
+    /// Not opened yet.
+    Closed,
+
+    /// Playing on the default audio device.
+    Device {
+        /// Must not be dropped until the playing process finishes.
+        _stream: OutputStream,
+    },
+
+    /// Consuming audio in a thread without audio device.
+    Headless {
+        /// Set to stop the thread.
+        stop: Arc<AtomicBool>,
+
+        thread: Option<JoinHandle<()>>,
+    },
+}
+
+/// Audio output of the player.
+///
+/// All [Sink]s created by it are mixed together and sent to the backend configured in
+/// [`OutputConfig`], and in parallel to the fifo, http stream and analyzer if configured.
+///
+/// The backend is opened on first [`Output::new_sink`] and kept open since then.
+#[derive(AutoDebug)]
+pub struct Output {
+    config: OutputConfig,
+
+    /// Controller to add [Sink]s into the mixer.
+    #[debug_ignore]
+    controller: Arc<DynamicMixerController<f32>>,
+
+    /// Output of the mixer, moved into backend once opened.
+    #[debug_ignore]
+    mixer: Option<DynamicMixer<f32>>,
+
+    #[debug_ignore]
+    backend: Backend,
+
+    /// Outputs served on the http server.
+    http: HttpOutputs,
+
+    /// Tap of [`HttpOutputs::stream`], started before the backend opened so listeners get
+    /// silence till the first play.
+    #[debug_ignore]
+    http_stream_tap: Option<Tap>,
+}
+
+impl Output {
+    /// Construct with `config`, the backend is not opened yet.
+    ///
+    /// Also send to outputs in `http` if any.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to start the http stream.
+    pub fn new(config: OutputConfig, http: HttpOutputs) -> Result<Self> {
+        let (controller, mixer) = dynamic_mixer::mixer(OUTPUT_CHANNELS, OUTPUT_SAMPLE_RATE);
+        let http_stream_tap = match &http.stream {
+            Some(v) => {
+                info!("output to http stream");
+                Some(v.spawn_encoder()?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            config,
+            controller,
+            mixer: Some(mixer),
+            backend: Backend::Closed,
+            http,
+            http_stream_tap,
+        })
+    }
+
+    /// Create a new [Sink] playing on this output, open the backend if not opened yet.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to open the backend.
+    pub fn new_sink(&mut self) -> Result<Sink> {
+        self.open()?;
+        let (sink, source) = Sink::new_idle();
+        self.controller.add(source);
+        Ok(sink)
+    }
+
+    /// Open the backend if not opened yet.
+    fn open(&mut self) -> Result<()> {
+        if !matches!(self.backend, Backend::Closed) {
+            return Ok(());
+        }
+        let mut taps = vec![];
+        if let Some(fifo) = &self.config.fifo {
+            taps.push(spawn_fifo(fifo.clone())?);
+            info!("output to fifo {}", fifo.path.display());
+        }
+        if let Some(tap) = &self.http_stream_tap {
+            taps.push(tap.clone());
+        }
+        if let Some(analyzer) = &self.http.analyzer {
+            taps.push(analyzer.spawn()?);
+            info!("output to analyzer");
+        }
+        self.backend = match self.config.backend {
+            OutputBackend::Device => {
+                let (stream, handle) =
+                    OutputStream::try_default().context(t!("player.failedToInit"))?;
+                handle
+                    .play_raw(self.take_source(taps))
+                    .context(t!("player.failedToInit"))?;
+                info!("output to default audio device");
+                Backend::Device { _stream: stream }
+            }
+            OutputBackend::Null => {
+                info!("output to null");
+                self.spawn_headless(taps, None)?
+            }
+            OutputBackend::Wav => {
+                let path = self
+                    .config
+                    .path
+                    .clone()
+                    .context(t!("output.wavPathNotSet"))?;
+                let writer = create_wav(&path)?;
+                info!("output to wav file {}", path.display());
+                self.spawn_headless(taps, Some(writer))?
+            }
+        };
+        Ok(())
+    }
+
+    /// Take the mixed output, also send to `taps`.
+    ///
+    /// Only call this once when opening the backend.
+    fn take_source(&mut self, taps: Vec<Tap>) -> MixedSource {
+        MixedSource {
+            mixer: self.mixer.take().expect("mixer already taken"),
+            taps,
+            chunk: Vec::with_capacity(TAP_CHUNK_SAMPLES),
+        }
+    }
+
+    /// Start a thread consuming the mixed output at real-time speed, write it to `writer` if
+    /// any.
+    fn spawn_headless(
+        &mut self,
+        taps: Vec<Tap>,
+        writer: Option<WavWriter<BufWriter<File>>>,
+    ) -> Result<Backend> {
+        let source = self.take_source(taps);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop2 = stop.clone();
+        let thread = thread::Builder::new()
+            .name("headless-output".to_string())
+            .spawn(move || {
+                if let Err(e) = run_headless(source, writer, &stop2) {
+                    error!("headless output stopped with error: {e:#}");
+                }
+            })
+            .context(t!("player.failedToInit"))?;
+        Ok(Backend::Headless {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Backend::Headless { stop, thread } = &mut self.backend {
+            stop.store(true, Ordering::SeqCst);
+            if let Some(thread) = thread.take() {
+                if thread.join().is_err() {
+                    error!("headless output thread panicked");
+                }
+            }
+        }
+    }
+}
+
+/// Create a wav file at `path` to write mixed output.
+fn create_wav(path: &Path) -> Result<WavWriter<BufWriter<File>>> {
+    let spec = WavSpec {
+        channels: OUTPUT_CHANNELS,
+        sample_rate: OUTPUT_SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    WavWriter::create(path, spec)
+        .with_context(|| t!("output.failedToCreateWav", path = path.display()))
+}
+
+/// Consume `source` at real-time speed until `stop` is set, write samples to `writer` if
+/// any.
+fn run_headless(
+    mut source: MixedSource,
+    mut writer: Option<WavWriter<BufWriter<File>>>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let step_samples = HEADLESS_STEP_FRAMES * u32::from(OUTPUT_CHANNELS);
+    let start = Instant::now();
+    let mut last_flush = start;
+    let mut frames: u64 = 0;
+    while !stop.load(Ordering::SeqCst) {
+        for _ in 0..step_samples {
+            let sample = source.next().unwrap_or(0.0);
+            if let Some(writer) = writer.as_mut() {
+                writer.write_sample(sample)?;
+            }
+        }
+        frames += u64::from(HEADLESS_STEP_FRAMES);
+
+        if let Some(writer) = writer.as_mut() {
+            if last_flush.elapsed() >= WAV_FLUSH_INTERVAL {
+                writer.flush()?;
+                last_flush = Instant::now();
+            }
+        }
+
+        let expected = Duration::from_micros(frames * 1_000_000 / u64::from(OUTPUT_SAMPLE_RATE));
+        if let Some(wait) = expected.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+    if let Some(writer) = writer {
+        writer.finalize()?;
+    }
+    Ok(())
+}
@@ -1,15 +1,47 @@
 use std::fs::File;
-use std::io::BufReader;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::Arc;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Context, Result};
-use log::{debug, error, info};
+use anyhow::{anyhow, bail, Context, Result};
+use libmpax::dto::{
+    AbLoopModel, HistoryEntryModel, HistoryEvent, MetadataModel, MusicModel,
+    PlayMode as PlayModeModel, PlayerState, PlayerStatusModel, PlaylistEntryModel, PlaylistModel,
+    SleepModel,
+};
+use log::{debug, error, info, warn};
 use racros::AutoDebug;
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::source::EmptyCallback;
+use rodio::{Decoder, Sink, Source};
 use rust_i18n::t;
+use tokio::sync::oneshot;
 
+use crate::ab_loop::{AbLoop, AbLoopSource};
+use crate::config::{OutputConfig, ReplayGainConfig, ReplayGainMode, ResumeConfig};
+use crate::cue::CueSource;
+use crate::history::{now_millis, History, Listening};
+use crate::metadata::gain_factor;
+use crate::music::MusicState;
+use crate::network::{is_network_url, HttpStream, StreamInfo};
+use crate::output::{HttpOutputs, Output};
 use crate::playlist::Playlist;
+use crate::scrobble::{Listen, Scrobbler};
+use crate::shuffle::Shuffle;
+use crate::state::SavedState;
+
+/// Restart current [Audio] instead of playing the previous one on [`PlayAction::Previous`] if
+/// it has played longer than this.
+const PREVIOUS_RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// Interval to do periodic works in player main loop when no [`PlayAction`] comes.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Interval to save state while playing, so that it survives a crash.
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Length of the fade out before stopped by a [`Sleep`].
+const SLEEP_FADE_DURATION: Duration = Duration::from_secs(10);
 
 /// Actions can apply to the player.
 #[derive(AutoDebug)]
@@ -18,15 +50,62 @@ pub enum PlayAction {
     /// Play the given audio file with file path.
     Play(String),
 
+    /// Save the stream at the given url as an internet radio station in playlist.
+    AddStation(String),
+
     /// Pause the play process.
     Pause,
 
     /// Resume the play process.
     Resume,
 
+    /// Play the next [Audio] in playlist.
+    Next,
+
+    /// Play the previous [Audio] in playlist, or restart the current one if it has played
+    /// for a while.
+    Previous,
+
+    /// Seek to the given position in current playing [Audio], reply the result through the
+    /// embedded sender.
+    Seek {
+        position: SeekPosition,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Change the volume.
+    ///
+    /// Also unmute the [Player] if muted.
+    SetVolume(VolumeChange),
+
+    /// Mute the [Player] if not muted, otherwise restore the volume before muted.
+    ToggleMute,
+
+    /// Change the [`PlayMode`].
+    SetPlayMode(PlayMode),
+
+    /// Change the crossfade settings.
+    SetCrossfade(Crossfade),
+
+    /// Loop between the given start and end positions in current playing [Audio], or stop
+    /// looping if `None`, reply the result through the embedded sender.
+    SetAbLoop {
+        range: Option<(Duration, Duration)>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Stop later as the given [`Sleep`] says, or cancel the pending one if `None`.
+    SetSleep(Option<Sleep>),
+
+    /// Query current status of the [Player], reply through the embedded sender.
+    QueryStatus(#[debug_ignore] oneshot::Sender<PlayerStatusModel>),
+
+    /// Query contents of current playlist, reply through the embedded sender.
+    QueryPlaylist(#[debug_ignore] oneshot::Sender<PlaylistModel>),
+
     /// Stop the player.
     ///
-    /// This will quit the player main loop.
+    /// Release the current [Audio] but keep the main loop running.
     Stop,
 
     /// Turn to exit.
@@ -34,12 +113,79 @@ pub enum PlayAction {
     /// Only do this operation when going to quit the daemon
     /// gracefully.
     Exit,
+
+    /// The track with the given id reached its end.
+    ///
+    /// Sent by the [Sink] itself when the appended source finished, never send it from outside
+    /// the player.
+    TrackFinished(u64),
+}
+
+/// Target position of a [`PlayAction::Seek`].
+#[derive(AutoDebug, Clone, Copy)]
+pub enum SeekPosition {
+    /// Seek to the position counted from the start of [Audio].
+    Absolute(Duration),
+
+    /// Seek forward from current position.
+    Forward(Duration),
+
+    /// Seek backward from current position.
+    ///
+    /// Saturate at the start of [Audio].
+    Backward(Duration),
+}
+
+/// Volume change in a [`PlayAction::SetVolume`].
+///
+/// All values are in percent, result volume saturates in `0..=100`.
+#[derive(AutoDebug, Clone, Copy)]
+pub enum VolumeChange {
+    /// Set to the given volume.
+    Absolute(u8),
+
+    /// Turn up the volume by given steps.
+    Up(u8),
+
+    /// Turn down the volume by given steps.
+    Down(u8),
+}
+
+/// Crossfade settings of [Player].
+///
+/// Overlap the tail of current [Audio] with the head of next one.
+#[derive(AutoDebug, Clone, Copy, Default)]
+pub struct Crossfade {
+    /// Length of the overlap, zero disables crossfade.
+    pub duration: Duration,
+
+    /// Also crossfade when repeating the same [Audio] in [`PlayMode::RepeatSingle`].
+    pub repeat_single: bool,
+}
+
+/// When to stop the [Player] later, with volume faded out before stopped.
+#[derive(AutoDebug, Clone, Copy, PartialEq, Eq)]
+pub enum Sleep {
+    /// Stop after the given time.
+    Timer(Duration),
+
+    /// Stop at the end of current [Audio].
+    AfterCurrent,
+}
+
+/// A pending [`Sleep`] in [Player].
+#[derive(AutoDebug, Clone, Copy)]
+enum SleepDeadline {
+    /// Stop at the given time.
+    At(Instant),
+
+    /// Stop at the end of current [Audio].
+    AfterCurrent,
 }
 
 /// Defines all [Player] running modes.
-#[derive(AutoDebug, PartialEq)]
-#[allow(unused)]
-enum PlayMode {
+#[derive(AutoDebug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
     /// Repeat to play the current playlist.
     RepeatPlaylist,
 
@@ -48,34 +194,131 @@ enum PlayMode {
 
     /// Randomly play [Audio]s in playlist.
     Random,
+
+    /// Play the current playlist in order, stop after the last one finished.
+    Sequential,
+}
+
+impl From<&PlayMode> for PlayModeModel {
+    fn from(value: &PlayMode) -> Self {
+        match value {
+            PlayMode::RepeatPlaylist => Self::RepeatPlaylist,
+            PlayMode::RepeatSingle => Self::RepeatSingle,
+            PlayMode::Random => Self::Random,
+            PlayMode::Sequential => Self::Sequential,
+        }
+    }
+}
+
+impl From<PlayModeModel> for PlayMode {
+    fn from(value: PlayModeModel) -> Self {
+        match value {
+            PlayModeModel::RepeatPlaylist => Self::RepeatPlaylist,
+            PlayModeModel::RepeatSingle => Self::RepeatSingle,
+            PlayModeModel::Random => Self::Random,
+            PlayModeModel::Sequential => Self::Sequential,
+        }
+    }
 }
 
 /// Source type of [Audio]s.
 ///
 /// Where this [Audio] came from.
 #[derive(AutoDebug, Clone)]
-#[allow(unused)]
 pub enum AudioSource {
     /// An audio file, file path embedded.
     File(String),
 
-    /// Network stream.
-    ///
-    /// **Not implemented**
+    /// Network stream over http, url embedded.
     Network(String),
 }
 
+impl AudioSource {
+    /// Get the source of `path`, http(s) urls are [`AudioSource::Network`].
+    fn new(path: &str) -> Self {
+        if is_network_url(path) {
+            Self::Network(path.to_string())
+        } else {
+            Self::File(path.to_string())
+        }
+    }
+
+    /// Open the source for reading.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to open the file.
+    /// * When failed to connect to the network stream.
+    fn open(&self) -> Result<AudioReader> {
+        match self {
+            Self::File(path) => Ok(AudioReader::File(BufReader::new(
+                File::open(path).context(t!("player.failedToOpenAudioFile", path = path))?,
+            ))),
+            Self::Network(url) => Ok(AudioReader::Network(
+                HttpStream::open(url).context(t!("player.failedToOpenAudioStream", url = url))?,
+            )),
+        }
+    }
+}
+
+/// Raw data of an [`AudioSource`], to feed the [Decoder].
+#[derive(AutoDebug)]
+enum AudioReader {
+    File(#[debug_value = "BufReader<File>"] BufReader<File>),
+    Network(HttpStream),
+}
+
+impl AudioReader {
+    /// Get the [`StreamInfo`] if reading a network stream.
+    fn stream_info(&self) -> Option<StreamInfo> {
+        match self {
+            Self::File(_) => None,
+            Self::Network(v) => Some(v.info()),
+        }
+    }
+}
+
+impl Read for AudioReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(v) => v.read(buf),
+            Self::Network(v) => v.read(buf),
+        }
+    }
+}
+
+impl Seek for AudioReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(v) => v.seek(pos),
+            Self::Network(v) => v.seek(pos),
+        }
+    }
+}
+
 /// Source of audio for [Player] to play.
 #[derive(AutoDebug)]
-#[allow(unused)]
 struct Audio {
-    source: AudioSource,
-    #[debug_value = "Decoder<File>"]
-    decoder: Decoder<BufReader<File>>,
+    /// Decoded samples, limited to the range of track if is a track in cue sheet.
+    #[debug_value = "Decoder<AudioReader>"]
+    decoder: Box<dyn Source<Item = i16> + Send>,
+
+    /// Information of the network stream, `None` for files.
+    stream_info: Option<StreamInfo>,
+}
+
+impl Audio {
+    /// Get the total duration, `None` if unknown or a live stream.
+    fn total_duration(&self) -> Option<Duration> {
+        if self.stream_info.as_ref().is_some_and(StreamInfo::live) {
+            return None;
+        }
+        self.decoder.total_duration()
+    }
 }
 
 /// Status of [Player].
-#[derive(AutoDebug, Clone, PartialEq)]
+#[derive(AutoDebug, Clone, PartialEq, Eq)]
 enum PlayerStatus {
     /// Initializing.
     Initial,
@@ -90,6 +333,58 @@ enum PlayerStatus {
     Stopped,
 }
 
+impl From<&PlayerStatus> for PlayerState {
+    fn from(value: &PlayerStatus) -> Self {
+        match value {
+            PlayerStatus::Initial => Self::Initial,
+            PlayerStatus::Playing => Self::Playing,
+            PlayerStatus::Paused => Self::Paused,
+            PlayerStatus::Stopped => Self::Stopped,
+        }
+    }
+}
+
+/// A track decoded and appended to [`Player::sink`] after the current one.
+///
+/// Starts right after the current one finished without any gap.
+#[derive(AutoDebug)]
+struct PreloadedTrack {
+    /// Id of the track, see [`Player::track_id`].
+    id: u64,
+
+    /// File path of the track.
+    file_path: String,
+
+    /// Total duration of the track, `None` if unknown.
+    duration: Option<Duration>,
+
+    /// The [`PlayMode`] this track was chosen in.
+    ///
+    /// The track is not continued if [`PlayMode`] changed since then.
+    play_mode: PlayMode,
+
+    /// Information of the network stream, `None` for files.
+    stream_info: Option<StreamInfo>,
+
+    /// A-B loop of the track.
+    ab_loop: AbLoop,
+}
+
+/// A [Sink] fading out during crossfade, while the next [Audio] fading in.
+struct FadingSink {
+    /// The sink fading out.
+    sink: Sink,
+
+    /// Track id of [Audio] in the sink.
+    track_id: u64,
+
+    /// Total duration of [Audio] in the sink.
+    duration: Duration,
+
+    /// Length of the fade.
+    fade: Duration,
+}
+
 /// The player to handle playing audio tasks.
 #[derive(AutoDebug)]
 pub struct Player {
@@ -99,17 +394,55 @@ pub struct Player {
     /// File path of last played file.
     last_played_file_path: Option<String>,
 
+    /// Id of the track currently playing in [`Self::sink`].
+    ///
+    /// Used to tell outdated [`PlayAction::TrackFinished`] events apart.
+    track_id: u64,
+
+    /// Id for the next track appended to [`Self::sink`].
+    next_track_id: u64,
+
+    /// The track queued after current one, for gapless playback.
+    preloaded: Option<PreloadedTrack>,
+
+    /// Total duration of current [Audio], `None` if unknown.
+    current_duration: Option<Duration>,
+
+    /// Information of current [Audio] if it's a network stream.
+    stream_info: Option<StreamInfo>,
+
+    /// A-B loop of current [Audio], also tracks its position.
+    ab_loop: AbLoop,
+
     /// Sink to post [Audio] sources to audio devices.
     #[debug_ignore]
-    sink: Arc<Sink>,
+    sink: Sink,
 
+    /// The previous sink still fading out in crossfade.
     #[debug_ignore]
-    stream: Option<Arc<OutputStream>>,
+    fading: Option<FadingSink>,
+
+    /// The output all sinks play on, opened on first play and kept open since then.
+    output: Output,
+
+    /// Volume in percent, kept across [Audio]s.
+    volume: u8,
+
+    /// Muted or not.
+    ///
+    /// [`Self::volume`] is kept when muted so that it can be restored.
+    muted: bool,
 
     /// Player running mode, decide the action when current playing
     /// [Audio] finished.
     play_mode: PlayMode,
 
+    /// Crossfade settings.
+    crossfade: Crossfade,
+
+    /// Whether crossfade out of current [Audio] was already tried, so that it's tried only once.
+    crossfade_attempted: bool,
+
     /// Internal sender to control operations.
     tx: Sender<PlayAction>,
 
@@ -118,106 +451,646 @@ pub struct Player {
 
     /// Current playlist
     playlist: Playlist,
-}
 
-/// FIXME: Check if is safe or use another way to achieve this.
-unsafe impl Send for Player {}
+    /// Play order used in [`PlayMode::Random`].
+    shuffle: Shuffle,
+
+    /// Config of saving and restoring state.
+    resume: ResumeConfig,
+
+    /// When the state was saved last time.
+    state_saved_at: Instant,
+
+    /// Listening history.
+    history: History,
+
+    /// Current music being listened, `None` if stopped.
+    listening: Option<Listening>,
+
+    /// Submit listens to scrobbling service, `None` if disabled.
+    scrobbler: Option<Scrobbler>,
+
+    /// Count of music failed to play in a row, reset once one played.
+    failed_in_row: usize,
+
+    /// Error stopped the player, reported in status until next one played.
+    error: Option<String>,
+
+    /// When to stop the player later, `None` if not going to.
+    sleep: Option<SleepDeadline>,
+
+    /// `ReplayGain` settings, applied when each [Audio] opened.
+    replay_gain: ReplayGainConfig,
+}
 
 /// Implementation of [Player].
 impl Player {
     /// Construct a new instance of [Player].
-    ///
-    /// # Errors
-    ///
-    /// * When failed to sink the output device.
-    pub fn new(tx: Sender<PlayAction>, rx: Receiver<PlayAction>) -> Result<Self> {
-        Ok(Player {
+    pub fn new(
+        tx: Sender<PlayAction>,
+        rx: Receiver<PlayAction>,
+        output: Output,
+        resume: ResumeConfig,
+        history: History,
+        scrobbler: Option<Scrobbler>,
+        replay_gain: ReplayGainConfig,
+    ) -> Self {
+        Self {
             status: PlayerStatus::Initial,
             last_played_file_path: None,
-            sink: Arc::new(Sink::new_idle().0),
-            stream: None,
+            track_id: 0,
+            next_track_id: 1,
+            preloaded: None,
+            current_duration: None,
+            stream_info: None,
+            ab_loop: AbLoop::new(),
+            sink: Sink::new_idle().0,
+            fading: None,
+            output,
+            volume: 100,
+            muted: false,
             play_mode: PlayMode::RepeatPlaylist,
+            crossfade: Crossfade::default(),
+            crossfade_attempted: false,
             tx,
             rx,
             playlist: Playlist::new("default".to_string()),
-        })
+            shuffle: Shuffle::new(),
+            resume,
+            state_saved_at: Instant::now(),
+            history,
+            listening: None,
+            scrobbler,
+            failed_in_row: 0,
+            error: None,
+            sleep: None,
+            replay_gain,
+        }
+    }
+
+    /// Restore the state saved when daemon exited last time.
+    ///
+    /// Playlist with play statistics is always restored. If resuming enabled, current music is
+    /// also loaded at the saved position and paused, or keeps playing if configured to auto play.
+    /// Internet radio stations are only reconnected in auto play.
+    ///
+    /// Listening to the restored music starts once it's resumed, see [`Self::resume`].
+    ///
+    /// Do nothing if nothing saved.
+    pub fn restore_state(&mut self) {
+        let state = match SavedState::load() {
+            Ok(Some(v)) => v,
+            Ok(None) => return,
+            Err(e) => {
+                error!("{e:#}");
+                return;
+            }
+        };
+        self.playlist = Playlist::with_music(state.playlist_name, state.music);
+        self.play_mode = state.play_mode.into();
+        self.volume = state.volume.min(100);
+        self.muted = state.muted;
+        info!(
+            "restored playlist {} with {} music",
+            self.playlist.name(),
+            self.playlist.music_count()
+        );
+
+        if !self.resume.enabled {
+            return;
+        }
+        let Some(current) = state.current.filter(|x| self.playlist.contains(x)) else {
+            return;
+        };
+        let station = self.playlist.is_station(&current);
+        if station && !self.resume.auto_play {
+            self.last_played_file_path = Some(current);
+            return;
+        }
+        // Always load paused to not play from the start before seeking to the saved position.
+        if let Err(e) = self.load_file(current.as_str(), true) {
+            error!("failed to restore {current}: {e:#}");
+            self.mark_unplayable(current.as_str());
+            self.last_played_file_path = Some(current);
+            return;
+        }
+        self.status = PlayerStatus::Paused;
+        self.shuffle.played(current.as_str());
+        self.playlist
+            .set_state(current.as_str(), MusicState::Exists);
+        if !station && state.position_ms > 0 {
+            if let Err(e) = self.seek_sink(Duration::from_millis(state.position_ms)) {
+                error!("{e}");
+            }
+        }
+        info!("restored {current} at {}ms", state.position_ms);
+        self.last_played_file_path = Some(current);
+        self.preload_next();
+        if self.resume.auto_play {
+            self.resume();
+        }
+    }
+
+    /// Save current state to restore on next startup.
+    ///
+    /// Current music and position are only saved if resuming enabled, the playlist with play
+    /// statistics is always saved.
+    fn save_state(&mut self) {
+        self.state_saved_at = Instant::now();
+        let position = match self.status {
+            PlayerStatus::Playing | PlayerStatus::Paused if self.resume.enabled => {
+                self.ab_loop.position()
+            }
+            _ => Duration::ZERO,
+        };
+        let state = SavedState {
+            playlist_name: self.playlist.name().to_string(),
+            music: self.playlist.music().to_vec(),
+            current: self
+                .last_played_file_path
+                .clone()
+                .filter(|_| self.resume.enabled),
+            position_ms: duration_to_millis(position),
+            play_mode: (&self.play_mode).into(),
+            volume: self.volume,
+            muted: self.muted,
+        };
+        match state.save() {
+            Ok(()) => debug!("state saved"),
+            Err(e) => error!("{e:#}"),
+        }
     }
 
     /// Play the audio file from given [path].
     ///
+    /// Returns as soon as the audio started, a [`PlayAction::TrackFinished`] is sent through
+    /// `self.tx` when it reaches the end.
+    ///
+    /// The next one to play is preloaded right after, see [`Self::preload_next`].
+    ///
     /// # Errors
     ///
     /// * When failed to open [Audio] file path.
     /// * When failed to decode [Audio] file resource.
+    /// * When failed to sink the output device.
     ///
     /// **Only call this function in main loop.**
     ///
     /// * Other operations should be actions send through `self.tx`.
-    async fn play_file(&mut self, path: &str) -> Result<()> {
-        let file = BufReader::new(
-            File::open(path).context(t!("player.canNotOpenAudioFile", path = path))?,
-        );
-        let source =
-            Decoder::new(file).context(t!("player.failedToDecodeAudioFile", path = path))?;
-        // The _stream is an OutputStream, which should not be dropped until the playing process
-        // finishes, and it's not Send so keep in the player.
-        let (_stream, stream_handle) = OutputStream::try_default()?;
-        let sink = Sink::try_new(&stream_handle).context(t!("player.failedToInit"))?;
-        self.sink.stop();
-        self.sink.clear();
-        self.sink = Arc::new(sink);
-        self.sink.append(source);
+    fn play_file(&mut self, path: &str) -> Result<()> {
+        self.load_file(path, false)?;
+        self.end_listening(HistoryEvent::Skip);
         self.status = PlayerStatus::Playing;
-        self.stream = Some(Arc::new(_stream));
-        let sink2 = self.sink.clone();
+        self.last_played_file_path = Some(path.to_string());
+        self.shuffle.played(path);
+        self.playlist.set_state(path, MusicState::Exists);
+        self.failed_in_row = 0;
+        self.error = None;
+        self.start_listening(path);
+        info!("start playing file {path}");
 
-        let handler = tokio::spawn(futures::future::lazy(move |_| {
-            info!("start player thread");
-            sink2.play();
-            sink2.sleep_until_end();
-            info!("end player thread");
-        }));
-        let _ = tokio::join!(handler);
+        self.preload_next();
+        Ok(())
+    }
 
-        self.last_played_file_path = Some(path.to_string());
-        info!("play file stopped");
+    /// Replace [`Self::sink`] with a new one playing the audio at `path`, paused if `paused`.
+    ///
+    /// Everything in the previous sink is dropped, including the preloaded track. Listening is
+    /// left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to open or decode the audio at `path`.
+    /// * When failed to sink the output device.
+    fn load_file(&mut self, path: &str, paused: bool) -> Result<()> {
+        let audio = self.open_audio(path)?;
+        let sink = self.output.new_sink()?;
+        if paused {
+            sink.pause();
+        }
+        self.discard_preloaded();
+        self.stop_fading();
+        self.sink.stop();
+        self.sink = sink;
+        self.apply_volume();
+
+        self.current_duration = audio.total_duration();
+        self.crossfade_attempted = false;
+        self.stream_info = audio.stream_info;
+        let gain = self.replay_gain_factor(path);
+        (self.track_id, self.ab_loop) = self.append_source(audio.decoder.amplify(gain));
         Ok(())
     }
 
-    /// Play next music in current playlist.
-    pub async fn play_next(&mut self) -> Result<()> {
-        if self.last_played_file_path.is_none() {
-            error!("failed to play next one: no one played ever");
-            return Ok(());
+    /// Open and decode the audio at `path`, either a file path or an http(s) url.
+    ///
+    /// Tracks in cue sheet are played from the range in their audio file.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to open [Audio] file path or connect to the url.
+    /// * When failed to decode [Audio] file resource.
+    fn open_audio(&self, path: &str) -> Result<Audio> {
+        let cue = self.playlist.cue_track(path);
+        let source = cue.as_ref().map_or_else(
+            || AudioSource::new(path),
+            |x| AudioSource::File(x.file_path.clone()),
+        );
+        let reader = source.open()?;
+        let stream_info = reader.stream_info();
+        let decoder =
+            Decoder::new(reader).context(t!("player.failedToDecodeAudioFile", path = path))?;
+        let decoder: Box<dyn Source<Item = i16> + Send> = match cue {
+            Some(v) => Box::new(CueSource::new(decoder, &v)),
+            None => Box::new(decoder),
+        };
+        Ok(Audio {
+            decoder,
+            stream_info,
+        })
+    }
+
+    /// Append `source` to the end of [`Self::sink`], followed by a callback sending
+    /// [`PlayAction::TrackFinished`] once `source` is drained.
+    ///
+    /// Return the track id of `source` and the [`AbLoop`] controlling it.
+    fn append_source<S>(&mut self, source: S) -> (u64, AbLoop)
+    where
+        S: Source<Item = i16> + Send + 'static,
+    {
+        let track_id = self.next_track_id;
+        self.next_track_id += 1;
+        let tx = self.tx.clone();
+        let ab_loop = AbLoop::new();
+        self.sink.append(AbLoopSource::new(source, ab_loop.clone()));
+        self.sink
+            .append(EmptyCallback::<f32>::new(Box::new(move || {
+                if let Err(e) = tx.send(PlayAction::TrackFinished(track_id)) {
+                    error!("failed to notify track {track_id} finished: {e}");
+                }
+            })));
+        (track_id, ab_loop)
+    }
+
+    /// Get the factor to amplify the music at `file_path` by, according to its `ReplayGain` tags
+    /// and [`Self::replay_gain`].
+    ///
+    /// Lowered to keep the peak below full scale if preventing clipping. Return 1.0 if
+    /// `ReplayGain` disabled or the music has no gain tags.
+    fn replay_gain_factor(&mut self, file_path: &str) -> f32 {
+        let config = &self.replay_gain;
+        if config.mode == ReplayGainMode::Off {
+            return 1.0;
         }
-        let next_one_index = self
+        let Some((gain, peak)) = self
             .playlist
-            .next_of_path(self.last_played_file_path.as_ref().unwrap().as_str());
-        if next_one_index.is_none() {
-            error!("failed to play next one: index of next one not found in playlist");
+            .load_metadata(file_path)
+            .and_then(|x| x.replay_gain().select(config.mode))
+        else {
+            debug!("no replay gain in {file_path}");
+            return 1.0;
+        };
+        let factor = gain_factor(gain, peak, config);
+        info!(
+            "apply {} replay gain {gain}dB with peak {peak:?} to {file_path}, factor {factor}",
+            config.mode.to_string()
+        );
+        factor
+    }
+
+    /// Decode the one to play after current one and append it to [`Self::sink`], so that it
+    /// starts without any gap.
+    ///
+    /// Do nothing if there is nothing to play next, e.g. reached the end of playlist in
+    /// [`PlayMode::Sequential`], or the next one will be crossfaded in.
+    ///
+    /// Internet radio stations never end, nothing is preloaded after them, and they are not
+    /// preloaded either to avoid buffering a live stream in advance.
+    fn preload_next(&mut self) {
+        if self.crossfade_duration().is_some() || self.is_playing_station() {
+            return;
+        }
+        let next = if self.play_mode == PlayMode::RepeatSingle {
+            self.last_played_file_path.clone()
+        } else {
+            self.next_file_path()
+        };
+        let Some(file_path) = next else {
+            return;
+        };
+        if self.playlist.is_station(&file_path) {
+            if self.play_mode == PlayMode::Random {
+                self.shuffle.put_back(file_path);
+            }
+            return;
+        }
+        match self.open_audio(file_path.as_str()) {
+            Ok(audio) => {
+                let duration = audio.total_duration();
+                let gain = self.replay_gain_factor(file_path.as_str());
+                let (id, ab_loop) = self.append_source(audio.decoder.amplify(gain));
+                debug!("preloaded track {id}: {file_path}");
+                self.preloaded = Some(PreloadedTrack {
+                    id,
+                    file_path,
+                    duration,
+                    play_mode: self.play_mode,
+                    stream_info: audio.stream_info,
+                    ab_loop,
+                });
+            }
+            Err(e) => {
+                error!("failed to preload {file_path}: {e:#}");
+                self.mark_unplayable(file_path.as_str());
+            }
+        }
+    }
+
+    /// Mark the music at `file_path` in playlist as unplayable after failed to open or decode
+    /// it.
+    ///
+    /// It's [`MusicState::NotExists`] if its audio file is gone, otherwise
+    /// [`MusicState::Corrupt`]. Network streams are left unchanged as they may come back later.
+    fn mark_unplayable(&mut self, file_path: &str) {
+        let audio_path = self
+            .playlist
+            .cue_track(file_path)
+            .map_or_else(|| file_path.to_string(), |x| x.file_path);
+        if is_network_url(audio_path.as_str()) || !self.playlist.contains(file_path) {
+            return;
+        }
+        let state = if Path::new(audio_path.as_str()).is_file() {
+            MusicState::Corrupt
+        } else {
+            MusicState::NotExists
+        };
+        warn!("mark {file_path} as {}", state.to_string());
+        self.playlist.set_state(file_path, state);
+    }
+
+    /// Skip the music at `file_path` which failed to play, try the next one according to
+    /// current [`PlayMode`].
+    ///
+    /// Stop with [`Self::error`] set once every music in playlist failed in a row. Internet
+    /// radio stations and music not in playlist are not skipped.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to send the [`PlayAction`].
+    fn skip_unplayable(&mut self, file_path: &str) -> Result<()> {
+        if !self.playlist.contains(file_path) || self.playlist.is_station(file_path) {
             return Ok(());
         }
-        let next_one_index_value = next_one_index.unwrap();
-        info!("try to play next one: {}", next_one_index_value);
-        let next_one = self.playlist.music_at(next_one_index_value);
-        if next_one.is_none() {
+        self.mark_unplayable(file_path);
+        self.failed_in_row += 1;
+        if self.failed_in_row >= self.playlist.music_count() {
+            let e = t!("player.noPlayableMusic", name = self.playlist.name()).to_string();
+            error!("{e}");
+            self.failed_in_row = 0;
+            self.stop();
+            self.error = Some(e);
+            return Ok(());
+        }
+        if let Some(v) = self.next_file_path_after(Some(file_path)) {
+            info!("skip unplayable {file_path}, try {v}");
+            self.tx.send(PlayAction::Play(v))?;
+        } else {
+            self.failed_in_row = 0;
+            self.stop();
+        }
+        Ok(())
+    }
+
+    /// Get the crossfade length to use at the end of current [Audio].
+    ///
+    /// Return `None` if not going to crossfade:
+    ///
+    /// * Crossfade disabled.
+    /// * In [`PlayMode::RepeatSingle`] but not configured to crossfade in it.
+    /// * Total duration of current [Audio] unknown or too short.
+    fn crossfade_duration(&self) -> Option<Duration> {
+        let fade = self.crossfade.duration;
+        if fade.is_zero()
+            || (self.play_mode == PlayMode::RepeatSingle && !self.crossfade.repeat_single)
+        {
+            return None;
+        }
+        self.current_duration
+            .filter(|x| *x > fade * 2)
+            .map(|_| fade)
+    }
+
+    /// Start crossfade if current [Audio] is close enough to its end.
+    ///
+    /// The next one is started in a new [Sink] with fade in, mixed with the current one fading
+    /// out in [`Self::fading`].
+    fn update_crossfade(&mut self) {
+        self.update_fading();
+        // Never reaches the end while looping, and nothing follows if stopping after it.
+        if self.status != PlayerStatus::Playing
+            || self.fading.is_some()
+            || self.ab_loop.range().is_some()
+            || matches!(self.sleep, Some(SleepDeadline::AfterCurrent))
+            || self.crossfade_attempted
+        {
+            return;
+        }
+        let (Some(fade), Some(duration)) = (self.crossfade_duration(), self.current_duration)
+        else {
+            return;
+        };
+        if duration.saturating_sub(self.ab_loop.position()) > fade {
+            return;
+        }
+        // Whatever happens below, let current one finish as usual instead of retrying on every
+        // tick.
+        self.crossfade_attempted = true;
+
+        let next = if self.play_mode == PlayMode::RepeatSingle {
+            self.last_played_file_path.clone()
+        } else {
+            self.next_file_path()
+        };
+        let Some(file_path) = next else {
+            // Nothing to crossfade into, let it finish as usual.
+            return;
+        };
+        let sink = match self.output.new_sink() {
+            Ok(v) => v,
+            Err(e) => {
+                error!("failed to start crossfade: {e:#}");
+                return;
+            }
+        };
+        let audio = match self.open_audio(file_path.as_str()) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("failed to start crossfade into {file_path}: {e:#}");
+                self.mark_unplayable(file_path.as_str());
+                return;
+            }
+        };
+
+        info!("crossfade into {file_path} in {fade:?}");
+        self.end_listening(HistoryEvent::Finish);
+        // Anything preloaded in the previous sink is dropped with it.
+        self.discard_preloaded();
+        let next_duration = audio.total_duration();
+        let gain = self.replay_gain_factor(file_path.as_str());
+        let previous_sink = std::mem::replace(&mut self.sink, sink);
+        self.fading = Some(FadingSink {
+            sink: previous_sink,
+            track_id: self.track_id,
+            duration,
+            fade,
+        });
+        self.apply_volume();
+        (self.track_id, self.ab_loop) =
+            self.append_source(audio.decoder.amplify(gain).fade_in(fade));
+        self.current_duration = next_duration;
+        self.crossfade_attempted = false;
+        self.stream_info = audio.stream_info;
+        self.shuffle.played(file_path.as_str());
+        self.start_listening(file_path.as_str());
+        self.last_played_file_path = Some(file_path);
+    }
+
+    /// Turn down the volume of [`Self::fading`] as it approaches its end, drop it when
+    /// finished.
+    fn update_fading(&mut self) {
+        let volume = self.volume_factor();
+        let Some(fading) = &self.fading else {
+            return;
+        };
+        let remaining = fading.duration.saturating_sub(fading.sink.get_pos());
+        if fading.sink.empty() || remaining.is_zero() {
+            self.stop_fading();
+            return;
+        }
+        let factor = (remaining.as_secs_f32() / fading.fade.as_secs_f32()).clamp(0.0, 1.0);
+        fading.sink.set_volume(volume * factor);
+    }
+
+    /// Stop and drop [`Self::fading`].
+    fn stop_fading(&mut self) {
+        if let Some(fading) = self.fading.take() {
+            fading.sink.stop();
+        }
+    }
+
+    /// Drop the preloaded track.
+    ///
+    /// Its source is left in [`Self::sink`], call this only before replacing or stopping the
+    /// sink.
+    fn discard_preloaded(&mut self) {
+        if let Some(track) = self.preloaded.take() {
+            debug!("discard preloaded track {}", track.id);
+            if track.play_mode == PlayMode::Random {
+                self.shuffle.put_back(track.file_path);
+            }
+        }
+    }
+
+    /// Get file path of the next music in current playlist according to current [`PlayMode`].
+    ///
+    /// See [`Self::next_file_path_after`].
+    fn next_file_path(&mut self) -> Option<String> {
+        let current = self.last_played_file_path.clone();
+        self.next_file_path_after(current.as_deref())
+    }
+
+    /// Get file path of the music after `current` in current playlist according to current
+    /// [`PlayMode`].
+    ///
+    /// * In [`PlayMode::Random`], the next one in shuffled order.
+    /// * In [`PlayMode::Sequential`], `None` if `current` is the last one.
+    /// * Otherwise the next one in playlist, turn to the first one after the last one.
+    fn next_file_path_after(&mut self, current: Option<&str>) -> Option<String> {
+        if self.play_mode == PlayMode::Random {
+            let next_one = self.shuffle.next(&self.playlist);
+            if next_one.is_none() {
+                error!("failed to play random one: playlist is empty");
+            }
+            return next_one;
+        }
+
+        let Some(current) = current else {
+            error!("failed to play next one: no one played ever");
+            return None;
+        };
+        if self.play_mode == PlayMode::Sequential && self.playlist.is_last(current) {
+            info!("reached the end of playlist {}", self.playlist.name());
+            return None;
+        }
+        let Some(next_one_index) = self.playlist.next_of_path(current) else {
+            error!("failed to play next one: index of next one not found in playlist");
+            return None;
+        };
+        let Some(next_one) = self.playlist.music_at(next_one_index) else {
             error!(
                 "failed to play next one: next one (index is {}) not found in playlist",
-                next_one_index_value
+                next_one_index
             );
-            return Ok(());
+            return None;
+        };
+        Some(next_one.file_path)
+    }
+
+    /// Play next music in current playlist according to current [`PlayMode`].
+    ///
+    /// See [`Self::next_file_path`] for the one to play.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to send the [`PlayAction`].
+    pub fn play_next(&mut self) -> Result<()> {
+        self.discard_preloaded();
+        if let Some(v) = self.next_file_path() {
+            info!("try to play next one: {v}");
+            self.tx.send(PlayAction::Play(v))?;
         }
-        self.tx
-            .send(PlayAction::Play(next_one.unwrap().file_path))?;
         Ok(())
     }
 
-    /// Pause the player, keep holding [Audio] resources.
+    /// Play previous music in current playlist according to current [`PlayMode`].
+    ///
+    /// * Restart current one if it has played more than [`PREVIOUS_RESTART_THRESHOLD`].
+    /// * In [`PlayMode::Random`], play the one played before current one.
+    /// * Otherwise play the previous one in playlist, turn to the last one before the first
+    ///   one.
     ///
     /// # Errors
     ///
-    /// * When player is not holding any [Audio] resource.
-    pub fn pause(&mut self) -> Result<()> {
+    /// * When failed to send the [`PlayAction`].
+    pub fn play_previous(&mut self) -> Result<()> {
+        let Some(last_played_file_path) = self.last_played_file_path.clone() else {
+            error!("failed to play previous one: no one played ever");
+            return Ok(());
+        };
+        let restart = (self.status == PlayerStatus::Playing || self.status == PlayerStatus::Paused)
+            && self.ab_loop.position() > PREVIOUS_RESTART_THRESHOLD;
+        let previous_one = if restart {
+            None
+        } else if self.play_mode == PlayMode::Random {
+            self.shuffle.previous()
+        } else {
+            self.playlist
+                .previous_of_path(last_played_file_path.as_str())
+                .and_then(|x| self.playlist.music_at(x))
+                .map(|x| x.file_path)
+        };
+        if let Some(v) = previous_one {
+            info!("try to play previous one: {v}");
+            self.tx.send(PlayAction::Play(v))?;
+        } else {
+            info!("restart current one: {last_played_file_path}");
+            self.tx.send(PlayAction::Play(last_played_file_path))?;
+        }
+        Ok(())
+    }
+
+    /// Pause the player, keep holding [Audio] resources.
+    pub fn pause(&mut self) {
         match &self.status {
             PlayerStatus::Initial | PlayerStatus::Paused | PlayerStatus::Stopped => {
                 // Do nothing.
@@ -225,108 +1098,693 @@ impl Player {
             PlayerStatus::Playing => {
                 self.status = PlayerStatus::Paused;
                 self.sink.pause();
+                if let Some(listening) = self.listening.as_mut() {
+                    listening.pause();
+                }
+                if let Some(fading) = &self.fading {
+                    fading.sink.pause();
+                }
             }
         }
-        Ok(())
     }
 
     /// Resume to play.
     ///
-    /// Do nothing if not in [PlayerStatus::Paused] state.
+    /// Start listening if not yet, as the music restored on startup is loaded without
+    /// listening.
+    ///
+    /// Do nothing if not in [`PlayerStatus::Paused`] state.
     pub fn resume(&mut self) {
         if self.status == PlayerStatus::Paused {
             self.sink.play();
+            if let Some(fading) = &self.fading {
+                fading.sink.play();
+            }
+            if let Some(listening) = self.listening.as_mut() {
+                listening.resume();
+            } else if let Some(v) = self.last_played_file_path.clone() {
+                self.start_listening(v.as_str());
+            }
             self.status = PlayerStatus::Playing;
         }
     }
 
+    /// Seek to `position` in current [Audio].
+    ///
+    /// Seeking beyond the end of [Audio] finishes it, just like it's played to the end.
+    ///
+    /// # Errors
+    ///
+    /// * When not in [`PlayerStatus::Playing`] or [`PlayerStatus::Paused`] state.
+    /// * When current [Audio] does not support seeking.
+    /// * When failed to seek in [Audio].
+    pub fn seek(&self, position: SeekPosition) -> Result<()> {
+        if self.status != PlayerStatus::Playing && self.status != PlayerStatus::Paused {
+            bail!(t!("player.canNotSeekNoAudioLoaded"));
+        }
+        let current = self.ab_loop.position();
+        let target = match position {
+            SeekPosition::Absolute(v) => v,
+            SeekPosition::Forward(v) => current.saturating_add(v),
+            SeekPosition::Backward(v) => current.saturating_sub(v),
+        };
+        debug!("seek from {current:?} to {target:?}");
+        self.seek_sink(target)
+    }
+
+    /// Seek [`Self::sink`] to `target`.
+    ///
+    /// # Errors
+    ///
+    /// * When current [Audio] does not support seeking.
+    /// * When failed to seek in [Audio].
+    fn seek_sink(&self, target: Duration) -> Result<()> {
+        self.sink.try_seek(target).map_err(|e| {
+            anyhow!(
+                "{}: {e}",
+                t!("player.failedToSeek", position = format!("{target:?}"))
+            )
+        })
+    }
+
+    /// Loop between the start and end in `range` in current [Audio], or stop looping if
+    /// `range` is `None`.
+    ///
+    /// Jump to the start if current position is out of `range`.
+    ///
+    /// # Errors
+    ///
+    /// * When no [Audio] loaded or playing an internet radio station.
+    /// * When `range` is empty or exceeds the end of current [Audio].
+    /// * When failed to seek to the start.
+    pub fn set_ab_loop(&self, range: Option<(Duration, Duration)>) -> Result<()> {
+        let Some((start, end)) = range else {
+            self.ab_loop.clear();
+            info!("A-B loop removed");
+            return Ok(());
+        };
+        if self.status != PlayerStatus::Playing && self.status != PlayerStatus::Paused {
+            bail!(t!("player.canNotLoopNoAudioLoaded"));
+        }
+        if self.is_playing_station() {
+            bail!(t!("player.canNotLoopStation"));
+        }
+        if start >= end || self.current_duration.is_some_and(|x| end > x) {
+            bail!(t!(
+                "player.invalidAbLoop",
+                start = format!("{start:?}"),
+                end = format!("{end:?}")
+            ));
+        }
+        let position = self.ab_loop.position();
+        if position < start || position >= end {
+            self.seek(SeekPosition::Absolute(start))?;
+        }
+        self.ab_loop.set(start, end);
+        info!("A-B loop set from {start:?} to {end:?}");
+        Ok(())
+    }
+
+    /// Change volume by `change` and unmute.
+    pub fn set_volume(&mut self, change: VolumeChange) {
+        self.volume = match change {
+            VolumeChange::Absolute(v) => v,
+            VolumeChange::Up(v) => self.volume.saturating_add(v),
+            VolumeChange::Down(v) => self.volume.saturating_sub(v),
+        }
+        .min(100);
+        self.muted = false;
+        self.apply_volume();
+        debug!("volume set to {}", self.volume);
+    }
+
+    /// Toggle muted state.
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.apply_volume();
+        debug!("muted: {}", self.muted);
+    }
+
+    /// Get the volume factor to apply to [Sink] according to current volume and muted state.
+    ///
+    /// Faded out in the last [`SLEEP_FADE_DURATION`] before stopped by [`Self::sleep`].
+    fn volume_factor(&self) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        let fade = self.sleep_remaining().map_or(1.0, |x| {
+            (x.as_secs_f32() / SLEEP_FADE_DURATION.as_secs_f32()).min(1.0)
+        });
+        f32::from(self.volume) / 100.0 * fade
+    }
+
+    /// Apply current volume and muted state to [`Self::sink`].
+    ///
+    /// [`Self::fading`] follows in next [`Self::update_fading`].
+    fn apply_volume(&self) {
+        self.sink.set_volume(self.volume_factor());
+    }
+
+    /// Change crossfade settings.
+    ///
+    /// If crossfade turned off, preload the next one to keep gapless playback.
+    pub fn set_crossfade(&mut self, crossfade: Crossfade) {
+        info!("crossfade changed to {crossfade:?}");
+        self.crossfade = crossfade;
+        if self.crossfade_duration().is_none()
+            && self.preloaded.is_none()
+            && (self.status == PlayerStatus::Playing || self.status == PlayerStatus::Paused)
+        {
+            self.preload_next();
+        }
+    }
+
+    /// Change [`PlayMode`].
+    ///
+    /// The track preloaded in the previous mode is dropped by reloading current [Audio] at its
+    /// position into a new [`Self::sink`], then the next one is preloaded in the new mode.
+    pub fn set_play_mode(&mut self, play_mode: PlayMode) {
+        info!("play mode changed to {play_mode:?}");
+        if play_mode == self.play_mode {
+            return;
+        }
+        self.play_mode = play_mode;
+        if self.status != PlayerStatus::Playing && self.status != PlayerStatus::Paused {
+            return;
+        }
+        if self.preloaded.is_some() {
+            let Some(file_path) = self.last_played_file_path.clone() else {
+                return;
+            };
+            let position = self.ab_loop.position();
+            let range = self.ab_loop.range();
+            // Load paused to not play from the start before seeking back.
+            match self.load_file(file_path.as_str(), true) {
+                Ok(()) => {
+                    if let Err(e) = self.seek_sink(position) {
+                        error!("{e}");
+                    }
+                    if let Some((start, end)) = range {
+                        self.ab_loop.set(start, end);
+                    }
+                    if self.status == PlayerStatus::Playing {
+                        self.sink.play();
+                    }
+                }
+                Err(e) => error!("failed to reload {file_path} to drop the preloaded one: {e:#}"),
+            }
+        }
+        if self.preloaded.is_none() {
+            self.preload_next();
+        }
+    }
+
+    /// Stop later as `sleep` says, replacing the pending one, or cancel the pending one if
+    /// `sleep` is `None`.
+    pub fn set_sleep(&mut self, sleep: Option<Sleep>) {
+        self.sleep = sleep.map(|x| match x {
+            Sleep::Timer(v) => SleepDeadline::At(Instant::now() + v),
+            Sleep::AfterCurrent => SleepDeadline::AfterCurrent,
+        });
+        if let Some(v) = sleep {
+            info!("going to stop: {v:?}");
+        } else {
+            info!("sleep cancelled");
+        }
+        self.apply_volume();
+    }
+
+    /// Get time left before stopped by [`Self::sleep`].
+    ///
+    /// `None` if not going to stop, or going to stop after current [Audio] with unknown
+    /// duration.
+    fn sleep_remaining(&self) -> Option<Duration> {
+        match self.sleep? {
+            SleepDeadline::At(v) => Some(v.saturating_duration_since(Instant::now())),
+            SleepDeadline::AfterCurrent => self
+                .current_duration
+                .map(|x| x.saturating_sub(self.ab_loop.position())),
+        }
+    }
+
+    /// Fade out as [`Self::sleep`] approaches, stop once the timer is up.
+    ///
+    /// Stopping after current [Audio] happens when it finishes, see
+    /// [`Self::on_track_finished`].
+    fn update_sleep(&mut self) {
+        let Some(sleep) = self.sleep else {
+            return;
+        };
+        if matches!(sleep, SleepDeadline::At(v) if v <= Instant::now()) {
+            info!("sleep timer is up, stop");
+            self.sleep = None;
+            self.stop();
+            return;
+        }
+        self.apply_volume();
+    }
+
     /// Stop the player and release the held [Audio] resources.
     ///
-    /// Do nothing if already in [PlayerStatus::Stopped] state.
+    /// Also cancel the pending [`Self::sleep`].
+    ///
+    /// Do nothing else if already in [`PlayerStatus::Stopped`] state.
     pub fn stop(&mut self) {
+        self.sleep = None;
         if self.status != PlayerStatus::Stopped {
+            self.end_listening(HistoryEvent::Skip);
+            self.discard_preloaded();
+            self.stop_fading();
             self.sink.stop();
+            self.stream_info = None;
+            self.current_duration = None;
             self.status = PlayerStatus::Stopped;
         }
     }
 
+    /// Build a snapshot of current status.
+    fn status_model(&self) -> PlayerStatusModel {
+        let current = self
+            .last_played_file_path
+            .as_ref()
+            .and_then(|x| self.playlist.index_of(x));
+        let elapsed = match self.status {
+            PlayerStatus::Playing | PlayerStatus::Paused => self.ab_loop.position(),
+            PlayerStatus::Initial | PlayerStatus::Stopped => Duration::ZERO,
+        };
+        PlayerStatusModel {
+            state: (&self.status).into(),
+            music: self
+                .last_played_file_path
+                .as_ref()
+                .and_then(|x| self.music_model(x)),
+            elapsed_ms: duration_to_millis(elapsed),
+            total_ms: self.current_duration.map(duration_to_millis),
+            play_mode: (&self.play_mode).into(),
+            crossfade_ms: duration_to_millis(self.crossfade.duration),
+            crossfade_repeat_single: self.crossfade.repeat_single,
+            volume: u32::from(self.volume),
+            muted: self.muted,
+            playlist_name: self.playlist.name().to_string(),
+            playlist_index: current,
+            playlist_length: self.playlist.music_count(),
+            error: self.error.clone(),
+            ab_loop: self.ab_loop.range().map(|(start, end)| AbLoopModel {
+                start_ms: duration_to_millis(start),
+                end_ms: duration_to_millis(end),
+            }),
+            sleep: self.sleep.map(|x| SleepModel {
+                after_current: matches!(x, SleepDeadline::AfterCurrent),
+                remaining_ms: self.sleep_remaining().map(duration_to_millis),
+            }),
+        }
+    }
+
+    /// Build a snapshot of current playlist.
+    fn playlist_model(&self) -> PlaylistModel {
+        PlaylistModel {
+            name: self.playlist.name().to_string(),
+            entries: self
+                .playlist
+                .music()
+                .iter()
+                .enumerate()
+                .map(|(index, music)| PlaylistEntryModel {
+                    index,
+                    music: music.into(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Build the model of the [`Music`](crate::music::Music) at `file_path` in playlist.
+    ///
+    /// Name and title of current network stream are filled in if it's the current one.
+    ///
+    /// Return `None` if not in playlist.
+    fn music_model(&self, file_path: &str) -> Option<MusicModel> {
+        let mut music: Option<MusicModel> = self
+            .playlist
+            .index_of(file_path)
+            .and_then(|x| self.playlist.music_at(x))
+            .map(|x| (&x).into());
+        if self.last_played_file_path.as_deref() != Some(file_path) {
+            return music;
+        }
+        if let (Some(music), Some(name)) = (
+            music.as_mut(),
+            self.stream_info.as_ref().and_then(StreamInfo::name),
+        ) {
+            music.file_name = name.to_string();
+        }
+        // Title of network streams changes while playing, e.g. songs on internet radio.
+        if let (Some(music), Some(title)) = (
+            music.as_mut(),
+            self.stream_info.as_ref().and_then(StreamInfo::title),
+        ) {
+            let metadata = music.metadata.get_or_insert_with(MetadataModel::default);
+            // Stream titles are usually in "Artist - Title" format.
+            match title.split_once(" - ") {
+                Some((artist, title)) => {
+                    metadata.title = Some(title.to_string());
+                    metadata.artist = vec![artist.to_string()];
+                }
+                None => metadata.title = Some(title),
+            }
+        }
+        music
+    }
+
+    /// Start listening to the music at `file_path`, recorded in history and statistics.
+    ///
+    /// Its metadata is parsed from tags if not yet, for scrobbling.
+    fn start_listening(&mut self, file_path: &str) {
+        self.playlist.load_metadata(file_path);
+        if let Some(stats) = self.playlist.stats_mut(file_path) {
+            stats.last_played_ms = Some(now_millis());
+        }
+        self.record_history(file_path, HistoryEvent::Start, Duration::ZERO);
+        let listening = Listening::new(file_path.to_string());
+        if let (Some(scrobbler), Some(listen)) = (
+            &self.scrobbler,
+            self.scrobble_listen(file_path, listening.started_at_ms),
+        ) {
+            scrobbler.now_playing(listen);
+        }
+        self.listening = Some(listening);
+    }
+
+    /// End listening to current music with `event`, recorded in history along with the time
+    /// heard, and counted in statistics.
+    ///
+    /// Do nothing if not listening.
+    fn end_listening(&mut self, event: HistoryEvent) {
+        if let Some(listening) = self.listening.take() {
+            if let Some(stats) = self.playlist.stats_mut(listening.file_path.as_str()) {
+                match event {
+                    HistoryEvent::Start => {}
+                    HistoryEvent::Finish => stats.play_count += 1,
+                    HistoryEvent::Skip => stats.skip_count += 1,
+                }
+            }
+            let heard = listening.heard();
+            self.record_history(listening.file_path.as_str(), event, heard);
+            if let (Some(scrobbler), Some(listen)) = (
+                &self.scrobbler,
+                self.scrobble_listen(listening.file_path.as_str(), listening.started_at_ms),
+            ) {
+                if listen.should_submit(heard) {
+                    scrobbler.listened(listen);
+                }
+            }
+        }
+    }
+
+    /// Build the [`Listen`] of the music at `file_path` to scrobble, using its [`Metadata`].
+    ///
+    /// Return `None` if title or artist unknown.
+    ///
+    /// [`Metadata`]: crate::metadata::Metadata
+    fn scrobble_listen(&self, file_path: &str, started_at_ms: u64) -> Option<Listen> {
+        let music = self
+            .playlist
+            .index_of(file_path)
+            .and_then(|x| self.playlist.music_at(x))?;
+        let metadata = music.metadata.as_ref()?;
+        let duration = Some(metadata.duration())
+            .filter(|x| !x.is_zero())
+            .or_else(|| {
+                self.current_duration
+                    .filter(|_| self.last_played_file_path.as_deref() == Some(file_path))
+            });
+        Some(Listen {
+            listened_at: started_at_ms / 1000,
+            artist: metadata.artist().first()?.clone(),
+            title: metadata.title()?.to_string(),
+            album: metadata.album().map(ToString::to_string),
+            duration_ms: duration.map(duration_to_millis),
+        })
+    }
+
+    /// Record `event` of the music at `file_path` in history.
+    fn record_history(&self, file_path: &str, event: HistoryEvent, heard: Duration) {
+        let Some(music) = self.music_model(file_path) else {
+            debug!("not record history of {file_path}: not in playlist");
+            return;
+        };
+        let entry = HistoryEntryModel {
+            time_ms: now_millis(),
+            event,
+            music,
+            heard_ms: duration_to_millis(heard),
+        };
+        if let Err(e) = self.history.record(&entry) {
+            error!("{e:#}");
+        }
+    }
+
+    /// Check whether current [Audio] is an internet radio station.
+    fn is_playing_station(&self) -> bool {
+        self.last_played_file_path
+            .as_ref()
+            .is_some_and(|x| self.playlist.is_station(x))
+    }
+
+    /// Handle the end of track with `track_id`.
+    ///
+    /// If there is a preloaded track, it's already playing, take it as the current one and
+    /// preload the next one. Otherwise decide what to play next according to current
+    /// [`PlayMode`].
+    ///
+    /// Events of tracks that are no longer the current one, or arrived after the player
+    /// stopped, are ignored.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to send the [`PlayAction`].
+    fn on_track_finished(&mut self, track_id: u64) -> Result<()> {
+        if self.fading.as_ref().is_some_and(|x| x.track_id == track_id) {
+            debug!("crossfade finished: {track_id}");
+            self.stop_fading();
+            return Ok(());
+        }
+        if track_id != self.track_id
+            || (self.status != PlayerStatus::Playing && self.status != PlayerStatus::Paused)
+        {
+            debug!("ignore outdated track finished event: {track_id}");
+            return Ok(());
+        }
+        info!("track {track_id} finished");
+        self.end_listening(HistoryEvent::Finish);
+
+        if matches!(self.sleep, Some(SleepDeadline::AfterCurrent)) {
+            info!("stop after current one");
+            self.stop();
+            return Ok(());
+        }
+
+        let play_mode = self.play_mode;
+        if let Some(track) = self.preloaded.take_if(|x| x.play_mode == play_mode) {
+            info!(
+                "continue with preloaded track {}: {}",
+                track.id, track.file_path
+            );
+            self.track_id = track.id;
+            self.current_duration = track.duration;
+            self.crossfade_attempted = false;
+            self.stream_info = track.stream_info;
+            self.ab_loop = track.ab_loop;
+            self.shuffle.played(track.file_path.as_str());
+            self.start_listening(track.file_path.as_str());
+            self.last_played_file_path = Some(track.file_path);
+            self.preload_next();
+            return Ok(());
+        }
+
+        // The preloaded one, if any, is chosen in another play mode, drop it.
+        self.discard_preloaded();
+        self.sink.stop();
+        self.status = PlayerStatus::Stopped;
+        // Use `self.tx` to trigger next loop to avoid recursively calling play
+        // functions.
+        if self.is_playing_station() {
+            if let Some(v) = self.last_played_file_path.as_ref() {
+                info!("station stream ended, reconnect: {v}");
+                self.tx.send(PlayAction::Play(v.to_owned()))?;
+            }
+            return Ok(());
+        }
+        match self.play_mode {
+            PlayMode::RepeatSingle => {
+                if let Some(v) = self.last_played_file_path.as_ref() {
+                    self.tx.send(PlayAction::Play(v.to_owned()))?;
+                }
+            }
+            PlayMode::RepeatPlaylist | PlayMode::Sequential | PlayMode::Random => {
+                self.play_next()?;
+            }
+        }
+        Ok(())
+    }
+
     /// Run the player main loop.
     ///
     /// Waits for future operations sent from correspond tx and do actions.
     ///
+    /// Playing audio does not block the loop, every [`PlayAction`] is handled as soon as it
+    /// arrives.
+    ///
     /// # Errors
     ///
-    /// * When failed to receive [PlayAction] from [rx].
-    pub async fn run_main_loop(&mut self) -> Result<()> {
+    /// * When failed to receive [`PlayAction`] from [rx].
+    pub fn run_main_loop(&mut self) -> Result<()> {
         loop {
-            let op = self
-                .rx
-                .recv()
-                .context(anyhow!(t!("player.failedToReceiveOperation")))?;
+            let op = match self.rx.recv_timeout(TICK_INTERVAL) {
+                Ok(v) => v,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.update_sleep();
+                    self.update_crossfade();
+                    if self.status == PlayerStatus::Playing
+                        && self.state_saved_at.elapsed() >= STATE_SAVE_INTERVAL
+                    {
+                        self.save_state();
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).context(anyhow!(t!("player.failedToReceiveOperation")));
+                }
+            };
             debug!("receive player action {op:#?}");
-            match &op {
+            match op {
                 PlayAction::Play(v) => {
                     // If music not exists in playlist, save it.
-                    if !self.playlist.contains(v) {
+                    if !self.playlist.contains(&v) && self.playlist.first_track_of_cue(&v).is_none()
+                    {
                         debug!(
                             "add music to playlist {}, triggered by play action on {}",
                             self.playlist.name(),
                             v
                         );
-                        match self.playlist.add_music_by_path(v) {
-                            Ok(v) => {
-                                info!("add {} music to playlist {}", v, self.playlist.name())
+                        match self.playlist.add_music_by_path(&v) {
+                            Ok(report) => {
+                                for (path, reason) in &report.skipped {
+                                    warn!("skip {}: {}", path.display(), reason);
+                                }
+                                info!(
+                                    "add {} music to playlist {}, {} skipped",
+                                    report.added,
+                                    self.playlist.name(),
+                                    report.skipped.len()
+                                );
                             }
                             Err(e) => error!("failed to add music {} to playlist: {}", v, e),
                         }
                     }
 
-                    // Play.
-                    if let Err(e) = self.play_file(v.as_str()).await {
+                    // Play the first track if given a cue sheet.
+                    let v = self.playlist.first_track_of_cue(&v).unwrap_or(v);
+                    if let Err(e) = self.play_file(v.as_str()) {
                         error!("{e:#?}");
-                    } else {
-                        debug!("start to play");
+                        self.skip_unplayable(v.as_str())?;
                     }
-                    info!("step into next loop");
-                    // Trigger next loop.
-                    // Use `self.tx` to trigger next loop to avoid recursively calling play
-                    // functions.
-                    match self.play_mode {
-                        PlayMode::RepeatPlaylist => {
-                            self.play_next().await?;
-                        }
-                        PlayMode::RepeatSingle => {
-                            self.tx.send(PlayAction::Play(v.to_owned()))?;
-                        }
-                        PlayMode::Random => unimplemented!(),
-                    };
                 }
+                PlayAction::AddStation(v) => match self.playlist.add_station(&v) {
+                    Ok(()) => info!("save station {} in playlist {}", v, self.playlist.name()),
+                    Err(e) => error!("failed to save station {}: {}", v, e),
+                },
+                PlayAction::Next => self.play_next()?,
+                PlayAction::Previous => self.play_previous()?,
                 PlayAction::Pause => {
-                    if let Err(e) = self.pause() {
-                        error!("{e}")
-                    } else {
-                        debug!("paused");
-                    }
+                    self.pause();
+                    debug!("paused");
                 }
                 PlayAction::Resume => self.resume(),
+                PlayAction::SetVolume(change) => self.set_volume(change),
+                PlayAction::ToggleMute => self.toggle_mute(),
+                PlayAction::SetCrossfade(crossfade) => self.set_crossfade(crossfade),
+                PlayAction::SetAbLoop { range, reply } => {
+                    reply_result(reply, self.set_ab_loop(range));
+                }
+                PlayAction::SetSleep(sleep) => self.set_sleep(sleep),
+                PlayAction::SetPlayMode(play_mode) => self.set_play_mode(play_mode),
+                PlayAction::Seek { position, reply } => reply_result(reply, self.seek(position)),
                 PlayAction::Stop => self.stop(),
                 PlayAction::Exit => {
                     // Stop the player and exit the app.
+                    self.save_state();
+                    // Exiting is not skipping current one.
+                    self.listening = None;
                     self.stop();
                     break;
                 }
+                PlayAction::QueryStatus(reply) => {
+                    if reply.send(self.status_model()).is_err() {
+                        error!("failed to reply player status: receiver dropped");
+                    }
+                }
+                PlayAction::QueryPlaylist(reply) => {
+                    if reply.send(self.playlist_model()).is_err() {
+                        error!("failed to reply playlist: receiver dropped");
+                    }
+                }
+                PlayAction::TrackFinished(track_id) => self.on_track_finished(track_id)?,
             }
         }
-        println!(">????");
         Ok(())
     }
 }
 
+/// Convert `duration` to milliseconds, saturate at [`u64::MAX`].
+fn duration_to_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Log the error in `result` if any, and reply `result` through `reply`.
+fn reply_result(reply: oneshot::Sender<Result<()>>, result: Result<()>) {
+    if let Err(e) = &result {
+        error!("{e}");
+    }
+    if reply.send(result).is_err() {
+        error!("failed to reply action result: receiver dropped");
+    }
+}
+
 /// Launch and run the player thread
-pub async fn launch_player_thread(tx: Sender<PlayAction>, rx: Receiver<PlayAction>) -> Result<()> {
+///
+/// The [Player] holds the [`Output`] which may hold a device stream not [Send], so it is
+/// constructed and driven inside a dedicated blocking thread.
+///
+/// # Errors
+///
+/// * When the player main loop exit with error.
+#[allow(clippy::too_many_arguments)]
+pub async fn launch_player_thread(
+    tx: Sender<PlayAction>,
+    rx: Receiver<PlayAction>,
+    config: OutputConfig,
+    http: HttpOutputs,
+    resume: ResumeConfig,
+    history: History,
+    scrobbler: Option<Scrobbler>,
+    replay_gain: ReplayGainConfig,
+) -> Result<()> {
     info!("player thread start");
-    let mut player = Player::new(tx, rx).context("player thread exit with error")?;
-    player.run_main_loop().await?;
+    tokio::task::spawn_blocking(move || {
+        let mut player = Player::new(
+            tx,
+            rx,
+            Output::new(config, http)?,
+            resume,
+            history,
+            scrobbler,
+            replay_gain,
+        );
+        player.restore_state();
+        player.run_main_loop()
+    })
+    .await
+    .context("player thread exit with error")??;
     info!("player thread exit");
     Ok(())
 }
@@ -1,10 +1,27 @@
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use log::warn;
 use racros::AutoDebug;
 
-use crate::music::{Music, MusicState};
+use crate::cue::{self, CueTrack};
+use crate::format::{self, SkipReason};
+use crate::history::now_millis;
+use crate::metadata::{Metadata, MetadataParser, SymphoniaParser};
+use crate::music::{Music, MusicState, MusicStats};
+use crate::network::is_network_url;
+
+/// Result of adding music to playlist by path.
+#[derive(AutoDebug, Default)]
+pub struct ImportReport {
+    /// Count of [`Music`] added.
+    pub added: usize,
+
+    /// Files not added, and the reason.
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
 
 #[derive(AutoDebug, Clone)]
 pub struct Playlist {
@@ -13,6 +30,9 @@ pub struct Playlist {
 
     /// All [`Music`] in playlist.
     music: Vec<Music>,
+
+    /// Version of the content, increased every time [`Self::music`] changed.
+    version: u64,
 }
 
 impl Playlist {
@@ -21,6 +41,16 @@ impl Playlist {
         Playlist {
             name,
             music: vec![],
+            version: 0,
+        }
+    }
+
+    /// Construct with existing `music`, e.g. restored from saved state.
+    pub const fn with_music(name: String, music: Vec<Music>) -> Self {
+        Self {
+            name,
+            music,
+            version: 0,
         }
     }
 
@@ -43,21 +73,111 @@ impl Playlist {
             .is_some()
     }
 
+    /// Get index (in playlist) of the music at `file_path`.
+    ///
+    /// * Return `None` if `file_path` not exists in playlist.
+    pub fn index_of(&self, file_path: &str) -> Option<usize> {
+        self.music.iter().position(|x| x.file_path == file_path)
+    }
+
+    /// Get the version of content.
+    ///
+    /// Changes every time [`Music`] added to or removed from playlist.
+    pub const fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Get all [`Music`] in playlist.
+    pub fn music(&self) -> &[Music] {
+        self.music.as_slice()
+    }
+
+    /// Get file paths of all [`Music`] in playlist.
+    pub fn file_paths(&self) -> impl Iterator<Item = &str> {
+        self.music.iter().map(|x| x.file_path.as_str())
+    }
+
+    /// Get the count of [`Music`] in playlist.
+    pub fn music_count(&self) -> usize {
+        self.music.len()
+    }
+
+    /// Get [`MusicStats`] of the [`Music`] at `file_path` to update.
+    ///
+    /// * Return `None` if `file_path` not exists in playlist.
+    pub fn stats_mut(&mut self, file_path: &str) -> Option<&mut MusicStats> {
+        self.music
+            .iter_mut()
+            .find(|x| x.file_path == file_path)
+            .map(|x| &mut x.stats)
+    }
+
+    /// Set the [`MusicState`] of the [`Music`] at `file_path`.
+    ///
+    /// Do nothing if `file_path` not exists in playlist.
+    pub fn set_state(&mut self, file_path: &str, state: MusicState) {
+        if let Some(music) = self.music.iter_mut().find(|x| x.file_path == file_path) {
+            music.state = state;
+        }
+    }
+
+    /// Get the [`Metadata`] of the [`Music`] at `file_path`, parse it from tags in the music
+    /// file if not parsed yet.
+    ///
+    /// * Return `None` if not exists in playlist, is a network stream, or failed to parse.
+    pub fn load_metadata(&mut self, file_path: &str) -> Option<&Metadata> {
+        let music = self.music.iter_mut().find(|x| x.file_path == file_path)?;
+        if music.metadata.is_none() && !music.station && !is_network_url(file_path) {
+            match SymphoniaParser::parse_metadata_from_file(file_path) {
+                Ok(v) => music.metadata = Some(v),
+                Err(e) => warn!("failed to parse metadata of {file_path}: {e:#}"),
+            }
+        }
+        music.metadata.as_ref()
+    }
+
+    /// Check whether the [`Music`] at `file_path` is an internet radio station.
+    pub fn is_station(&self, file_path: &str) -> bool {
+        self.music
+            .iter()
+            .any(|x| x.file_path == file_path && x.station)
+    }
+
+    /// Get the [`CueTrack`] of the [`Music`] at `file_path`.
+    ///
+    /// * Return `None` if not exists in playlist or not a track in cue sheet.
+    pub fn cue_track(&self, file_path: &str) -> Option<CueTrack> {
+        self.music
+            .iter()
+            .find(|x| x.file_path == file_path)
+            .and_then(|x| x.cue.clone())
+    }
+
+    /// Get file path of the first track in cue sheet at `sheet_path`.
+    ///
+    /// * Return `None` if the cue sheet not added to playlist.
+    pub fn first_track_of_cue(&self, sheet_path: &str) -> Option<String> {
+        self.music
+            .iter()
+            .find(|x| x.cue.as_ref().is_some_and(|x| x.sheet_path == sheet_path))
+            .map(|x| x.file_path.clone())
+    }
+
+    /// Check whether the [`Music`] at `file_path` is the last one in playlist.
+    pub fn is_last(&self, file_path: &str) -> bool {
+        self.music.last().is_some_and(|x| x.file_path == file_path)
+    }
+
     /// Get index (in playlist) of the previous music before the one at `file_path`.
     ///
     /// * Return `None` if `file_path` not exists in playlist.
     /// * Return the next music's index in playlist if found.
     /// * Return the last one if `file_path` is the first one.
-    #[allow(unused)]
     pub fn previous_of_path(&self, file_path: &str) -> Option<usize> {
         if self.music.is_empty() {
             return None;
         }
-        let current = self.music.iter().position(|x| x.file_path == file_path);
-        if current.is_none() {
-            return None;
-        }
-        let current_index = current.unwrap();
+        let current_index = self.index_of(file_path)?;
         // Return the last one if is the first one.
         if current_index == 0 {
             return Some(self.music.len() - 1);
@@ -74,11 +194,7 @@ impl Playlist {
         if self.music.is_empty() {
             return None;
         }
-        let current = self.music.iter().position(|x| x.file_path == file_path);
-        if current.is_none() {
-            return None;
-        }
-        let current_index = current.unwrap();
+        let current_index = self.index_of(file_path)?;
         // Return the first one if is the last one.
         if current_index >= self.music.len() - 1 {
             return Some(0);
@@ -94,80 +210,172 @@ impl Playlist {
     }
 
     /// Add music.
-    pub fn add_music(&mut self, music: Vec<Music>) {
+    ///
+    /// Added time in [`MusicStats`] is set to now.
+    pub fn add_music(&mut self, mut music: Vec<Music>) {
+        if music.is_empty() {
+            return;
+        }
+        let now = now_millis();
+        for x in &mut music {
+            x.stats.added_ms = now;
+        }
         self.music.extend(music);
+        self.version += 1;
     }
 
     /// Add music by specify the file path or folder path.
     ///
     /// * If [`path`] is a file, add it.
-    /// * If [`path`] is a directory, add all music under it (include subdirectory).
+    /// * If [`path`] is a cue sheet, add each track in it.
+    /// * If [`path`] is a directory, add all music under it (include subdirectory), audio
+    ///   files split by cue sheets are only added as cue tracks.
+    /// * If [`path`] is an http(s) url, add it as a network stream.
     /// * If [`path`] not exists, do nothing.
     ///
-    /// Return the number of [`Music`] successfully added.
-    pub fn add_music_by_path(&mut self, path: &str) -> Result<usize> {
+    /// Only supported audio files are added, detected by content and extension.
+    ///
+    /// Return the count of [`Music`] successfully added and the files skipped.
+    pub fn add_music_by_path(&mut self, path: &str) -> Result<ImportReport> {
+        if is_network_url(path) {
+            let file_name = path
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or(path)
+                .to_string();
+            self.add_music(vec![Music {
+                file_path: path.to_string(),
+                file_name,
+                state: MusicState::Unknown,
+                metadata: None,
+                station: false,
+                stats: MusicStats::default(),
+                cue: None,
+            }]);
+            return Ok(ImportReport {
+                added: 1,
+                skipped: vec![],
+            });
+        }
         self.traverse_dir_add_all(path)
     }
 
-    fn add_file_to_list(&mut self, path: &str) -> Result<Music> {
-        let pb = PathBuf::from(path);
-        let file_name = pb.file_name().unwrap().to_str().unwrap().to_string();
-        Ok(Music {
-            file_path: path.to_string(),
+    /// Save the stream at `url` as an internet radio station.
+    ///
+    /// Mark it as station if already in playlist, otherwise add it.
+    pub fn add_station(&mut self, url: &str) -> Result<()> {
+        if !is_network_url(url) {
+            bail!("station is not an http(s) url: {url}");
+        }
+        if !self.contains(url) {
+            self.add_music_by_path(url)?;
+        }
+        if let Some(music) = self.music.iter_mut().find(|x| x.file_path == url) {
+            music.station = true;
+        }
+        Ok(())
+    }
+
+    /// Build the [`Music`] of local file at `path`.
+    fn local_music(path: &Path) -> Music {
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Music {
+            file_path: path.to_string_lossy().to_string(),
             file_name,
             state: MusicState::Exists,
             metadata: None,
-        })
+            station: false,
+            stats: MusicStats::default(),
+            cue: None,
+        }
     }
 
-    fn traverse_dir_add_all(&mut self, path: &str) -> Result<usize> {
-        let mut count = 0;
+    fn traverse_dir_add_all(&mut self, path: &str) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
         let mut ret = vec![];
+        Self::collect_music(Path::new(path), &mut ret, &mut report)?;
+        // Audio files split by cue sheets are added as cue tracks only.
+        let cue_files: HashSet<String> = ret
+            .iter()
+            .filter_map(|x| x.cue.as_ref().map(|x| x.file_path.clone()))
+            .collect();
+        ret.retain(|x| x.cue.is_some() || !cue_files.contains(&x.file_path));
+        report.added = ret.len();
+        self.add_music(ret);
+        Ok(report)
+    }
+
+    /// Collect all supported audio files at `path` into `music`, recursively if is a directory.
+    ///
+    /// Files not imported are recorded in `report`.
+    fn collect_music(path: &Path, music: &mut Vec<Music>, report: &mut ImportReport) -> Result<()> {
         let info = fs::metadata(path)?;
         if info.is_dir() {
-            for entry in fs::read_dir(path)? {
-                let metadata = entry.as_ref().unwrap().metadata()?;
-                if metadata.is_dir() {
-                    count += self.traverse_dir_add_all(path)?;
+            let mut entries = fs::read_dir(path)?
+                .map(|x| x.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                if let Err(e) = Self::collect_music(&entry, music, report) {
+                    report
+                        .skipped
+                        .push((entry, SkipReason::Unreadable(e.to_string())));
                 }
-                let file_name = entry
-                    .as_ref()
-                    .unwrap()
-                    .path()
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-                ret.push(Music {
-                    file_path: path.to_string(),
-                    file_name,
-                    state: MusicState::Exists,
-                    metadata: None,
-                });
-                count += 1;
             }
-        } else if info.is_file() && path.ends_with(".mp3") {
-            ret.push(self.add_file_to_list(path)?);
+        } else if info.is_file()
+            && path
+                .extension()
+                .is_some_and(|x| x.eq_ignore_ascii_case("cue"))
+        {
+            match cue::parse(path) {
+                Ok(v) => music.extend(v),
+                Err(e) => report
+                    .skipped
+                    .push((path.to_path_buf(), SkipReason::InvalidCue(e.to_string()))),
+            }
+        } else if info.is_file() {
+            match format::detect(path) {
+                Ok(_) => music.push(Self::local_music(path)),
+                Err(e) => report.skipped.push((path.to_path_buf(), e)),
+            }
         }
-
-        self.add_music(ret);
-        Ok(count)
+        Ok(())
     }
 
     /// Remove music by specify the file path or folder path.
     ///
     /// Only remove from playlist, not delete from disk.
     ///
-    /// * If [`path`] is a file, remove it.
-    /// * If [`path`] is a directory, add all the music under it (include subdirectory) if in
+    /// * If [`path`] is a file, remove it, or all its tracks if it's a cue sheet.
+    /// * If [`path`] is a directory, remove all the music under it (include subdirectory) if in
     ///   playlist.
     /// * If [`path`] not exists, do nothing.
     ///
     /// Return the number of [`Music`] removed.
     #[allow(unused)]
     pub fn remove_music_by_path(&mut self, path: impl AsRef<str>) -> usize {
-        let _ = path;
-        unimplemented!()
+        let path = Path::new(path.as_ref());
+        if !path.exists() {
+            return 0;
+        }
+        let is_dir = path.is_dir();
+        let count = self.music.len();
+        self.music.retain(|x| {
+            let file_path = x
+                .cue
+                .as_ref()
+                .map_or(x.file_path.as_str(), |x| x.sheet_path.as_str());
+            let file_path = Path::new(file_path);
+            !(file_path == path || (is_dir && file_path.starts_with(path)))
+        });
+        let removed = count - self.music.len();
+        if removed > 0 {
+            self.version += 1;
+        }
+        removed
     }
 }
@@ -0,0 +1,635 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use log::{debug, error, info, warn};
+use racros::AutoDebug;
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::{state_dir, ScrobbleConfig, ScrobbleService};
+
+/// Name of the file saving listens failed to submit, in state directory.
+const QUEUE_FILE_NAME: &str = "scrobble-queue.json";
+
+/// Interval to retry submitting queued listens.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Timeout of each submission.
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Music shorter than this are never submitted as listened.
+const MIN_DURATION: Duration = Duration::from_secs(30);
+
+/// Music heard longer than this are submitted as listened, even if not heard half of it.
+const LISTEN_THRESHOLD: Duration = Duration::from_secs(4 * 60);
+
+/// Max count of listens waiting to submit, the earliest ones are dropped once exceeded.
+const MAX_QUEUED_LISTENS: usize = 1000;
+
+/// Last.fm errors fixed later, worth retrying: authentication failed (4), invalid session key
+/// (9), service offline (11), temporarily unavailable (16) and rate limit exceeded (29).
+const LASTFM_RETRYABLE_ERRORS: [u64; 5] = [4, 9, 11, 16, 29];
+
+/// A music listened, or being listened.
+#[derive(AutoDebug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Listen {
+    /// When started listening, in seconds since unix epoch.
+    pub listened_at: u64,
+
+    /// Artist name.
+    pub artist: String,
+
+    /// Track title.
+    pub title: String,
+
+    /// Album title.
+    pub album: Option<String>,
+
+    /// Duration of the track, `None` if unknown.
+    pub duration_ms: Option<u64>,
+}
+
+impl Listen {
+    /// Check whether heard enough to submit as listened.
+    ///
+    /// Music longer than 30 seconds counts once heard half of it or 4 minutes, whichever comes
+    /// first. Music with unknown duration counts after 4 minutes.
+    pub fn should_submit(&self, heard: Duration) -> bool {
+        self.duration_ms.map(Duration::from_millis).map_or_else(
+            || heard >= LISTEN_THRESHOLD,
+            |duration| duration >= MIN_DURATION && heard >= (duration / 2).min(LISTEN_THRESHOLD),
+        )
+    }
+}
+
+/// Request to the scrobbling thread.
+#[derive(AutoDebug)]
+enum Request {
+    /// Update the music being listened.
+    NowPlaying(Listen),
+
+    /// Submit a listened music.
+    Listened(Listen),
+}
+
+/// Result of a submission the service replied.
+enum Submission {
+    Accepted,
+
+    /// The service refused the listen, retrying will not help.
+    Rejected(String),
+}
+
+/// Submit listens to a scrobbling service in background.
+///
+/// Listens failed to submit are saved on disk and retried later, also after daemon restarted.
+#[derive(AutoDebug, Clone)]
+pub struct Scrobbler {
+    #[debug_ignore]
+    tx: Sender<Request>,
+}
+
+impl Scrobbler {
+    /// Start the scrobbling thread with `config`.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to start the thread.
+    pub fn spawn(config: ScrobbleConfig) -> Result<Self> {
+        let (tx, rx) = channel();
+        thread::Builder::new()
+            .name("scrobbler".to_string())
+            .spawn(move || {
+                // Blocking client can not be built in async context, build it here.
+                let client = match Client::builder().timeout(SUBMIT_TIMEOUT).build() {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!("{}: {e}", t!("scrobble.failedToInit"));
+                        return;
+                    }
+                };
+                let worker = Worker {
+                    config,
+                    client,
+                    queue: Queue::load(),
+                };
+                worker.run(&rx);
+            })
+            .context(t!("scrobble.failedToInit"))?;
+        Ok(Self { tx })
+    }
+
+    /// Tell the service `listen` started.
+    pub fn now_playing(&self, listen: Listen) {
+        if self.tx.send(Request::NowPlaying(listen)).is_err() {
+            error!("failed to update now playing: scrobbler stopped");
+        }
+    }
+
+    /// Submit `listen` as listened.
+    pub fn listened(&self, listen: Listen) {
+        if self.tx.send(Request::Listened(listen)).is_err() {
+            error!("failed to submit listen: scrobbler stopped");
+        }
+    }
+}
+
+/// Listens waiting to submit, saved on disk.
+struct Queue {
+    /// Path of the queue file, `None` if state directory not found.
+    path: Option<PathBuf>,
+
+    /// Listens, the earliest one first.
+    listens: Vec<Listen>,
+}
+
+impl Queue {
+    /// Load listens saved last time.
+    fn load() -> Self {
+        let path = state_dir().map(|x| x.join(QUEUE_FILE_NAME));
+        let listens = path
+            .as_ref()
+            .filter(|x| x.exists())
+            .and_then(|x| match fs::read_to_string(x) {
+                Ok(v) => serde_json::from_str(v.as_str())
+                    .inspect_err(|e| error!("invalid scrobble queue {}: {e}", x.display()))
+                    .ok(),
+                Err(e) => {
+                    error!("failed to read scrobble queue {}: {e}", x.display());
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { path, listens }
+    }
+
+    /// Add `listen` to the end, drop the earliest ones if more than [`MAX_QUEUED_LISTENS`].
+    fn push(&mut self, listen: Listen) {
+        self.listens.push(listen);
+        let excess = self.listens.len().saturating_sub(MAX_QUEUED_LISTENS);
+        if excess > 0 {
+            for listen in self.listens.drain(..excess) {
+                warn!("too many listens queued, dropped: {listen:?}");
+            }
+        }
+    }
+
+    /// Save listens on disk.
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            bail!(t!("state.stateDirNotFound"));
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .context(t!("scrobble.failedToSaveQueue", path = path.display()))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string(&self.listens)?)
+            .and_then(|()| fs::rename(&temp_path, path))
+            .context(t!("scrobble.failedToSaveQueue", path = path.display()))
+    }
+}
+
+/// State of the scrobbling thread.
+struct Worker {
+    config: ScrobbleConfig,
+    client: Client,
+    queue: Queue,
+}
+
+impl Worker {
+    /// Handle requests from `rx` until all [`Scrobbler`]s dropped, retry queued listens
+    /// periodically.
+    fn run(mut self, rx: &Receiver<Request>) {
+        info!(
+            "scrobbler started, submit to {} at {}, {} listens queued",
+            self.config.service.to_string(),
+            self.config.url(),
+            self.queue.listens.len()
+        );
+        self.flush();
+        loop {
+            match rx.recv_timeout(RETRY_INTERVAL) {
+                Ok(Request::NowPlaying(listen)) => {
+                    match self.submit(&listen, false) {
+                        Ok(Submission::Accepted) => debug!("now playing updated: {listen:?}"),
+                        Ok(Submission::Rejected(e)) => warn!("now playing rejected: {e}"),
+                        // Outdated soon, not worth retrying.
+                        Err(e) => warn!("failed to update now playing: {e:#}"),
+                    }
+                }
+                Ok(Request::Listened(listen)) => {
+                    self.queue.push(listen);
+                    self.flush();
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        info!("scrobbler stopped");
+    }
+
+    /// Submit queued listens in order, stop at the first failure and keep the rest queued.
+    fn flush(&mut self) {
+        if self.queue.listens.is_empty() {
+            return;
+        }
+        let mut done = 0;
+        for listen in &self.queue.listens {
+            match self.submit(listen, true) {
+                Ok(Submission::Accepted) => info!("listen submitted: {listen:?}"),
+                Ok(Submission::Rejected(e)) => warn!("listen rejected, dropped: {listen:?}: {e}"),
+                Err(e) => {
+                    warn!("failed to submit listen, retry later: {e:#}");
+                    break;
+                }
+            }
+            done += 1;
+        }
+        // Also save when nothing submitted, to keep newly queued ones on disk.
+        self.queue.listens.drain(..done);
+        if let Err(e) = self.queue.save() {
+            error!("{e:#}");
+        }
+    }
+
+    /// Submit `listen`, as listened if `listened` or as now playing.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to send the request or the service failed, worth retrying.
+    fn submit(&self, listen: &Listen, listened: bool) -> Result<Submission> {
+        let request = match self.config.service {
+            ScrobbleService::ListenBrainz => self.listenbrainz_request(listen, listened),
+            ScrobbleService::LastFm => self.lastfm_request(listen, listened),
+        };
+        let resp = request.send()?;
+        let status = resp.status();
+        let body = resp.text().unwrap_or_default();
+        // Last.fm replies errors in body, may be with status 200.
+        let error = serde_json::from_str::<serde_json::Value>(body.as_str())
+            .ok()
+            .and_then(|x| x.get("error").and_then(serde_json::Value::as_u64));
+        if status.is_success() && error.is_none() {
+            return Ok(Submission::Accepted);
+        }
+        // Auth errors and rate limits are fixed later, other client errors are not.
+        let retryable = matches!(status.as_u16(), 401 | 403 | 429)
+            || error.is_some_and(|x| LASTFM_RETRYABLE_ERRORS.contains(&x));
+        if !retryable && (status.is_success() || status.is_client_error()) {
+            return Ok(Submission::Rejected(format!("{status}: {body}")));
+        }
+        bail!("{status}: {body}")
+    }
+
+    /// Build the request to submit `listen` to `ListenBrainz`.
+    fn listenbrainz_request(
+        &self,
+        listen: &Listen,
+        listened: bool,
+    ) -> reqwest::blocking::RequestBuilder {
+        let mut payload = json!({
+            "track_metadata": {
+                "artist_name": listen.artist,
+                "track_name": listen.title,
+                "additional_info": {
+                    "media_player": "mpax",
+                },
+            },
+        });
+        if let Some(album) = &listen.album {
+            payload["track_metadata"]["release_name"] = json!(album);
+        }
+        if let Some(duration_ms) = listen.duration_ms {
+            payload["track_metadata"]["additional_info"]["duration_ms"] = json!(duration_ms);
+        }
+        let listen_type = if listened {
+            payload["listened_at"] = json!(listen.listened_at);
+            "single"
+        } else {
+            "playing_now"
+        };
+        let body = json!({
+            "listen_type": listen_type,
+            "payload": [payload],
+        });
+        self.client
+            .post(format!(
+                "{}/1/submit-listens",
+                self.config.url().trim_end_matches('/')
+            ))
+            .header(
+                AUTHORIZATION,
+                format!("Token {}", self.config.token.as_deref().unwrap_or_default()),
+            )
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+    }
+
+    /// Build the request to submit `listen` to Last.fm.
+    fn lastfm_request(&self, listen: &Listen, listened: bool) -> reqwest::blocking::RequestBuilder {
+        let method = if listened {
+            "track.scrobble"
+        } else {
+            "track.updateNowPlaying"
+        };
+        let mut params = vec![
+            ("method", method.to_string()),
+            ("artist", listen.artist.clone()),
+            ("track", listen.title.clone()),
+            ("api_key", self.config.api_key.clone().unwrap_or_default()),
+            ("sk", self.config.session_key.clone().unwrap_or_default()),
+        ];
+        if let Some(album) = &listen.album {
+            params.push(("album", album.clone()));
+        }
+        if let Some(duration_ms) = listen.duration_ms {
+            params.push(("duration", (duration_ms / 1000).to_string()));
+        }
+        if listened {
+            params.push(("timestamp", listen.listened_at.to_string()));
+        }
+        // Signature is the md5 of all parameters sorted by name, followed by the secret.
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        let mut signature = String::new();
+        for (k, v) in &params {
+            signature.push_str(k);
+            signature.push_str(v);
+        }
+        signature.push_str(self.config.api_secret.as_deref().unwrap_or_default());
+        params.push(("api_sig", format!("{:x}", md5::compute(signature))));
+        params.push(("format", "json".to_string()));
+        self.client.post(self.config.url()).form(&params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::Receiver;
+
+    use super::*;
+
+    /// A request received by [`serve`].
+    struct Received {
+        path: String,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    /// Reply `responses` in order to requests on a local port.
+    ///
+    /// Return the url of the listener and a receiver of requests received.
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap().to_string();
+                let mut authorization = None;
+                let mut length = 0;
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else {
+                        break;
+                    };
+                    match name.to_ascii_lowercase().as_str() {
+                        "authorization" => authorization = Some(value.to_string()),
+                        "content-length" => length = value.parse().unwrap(),
+                        _ => {}
+                    }
+                }
+                let mut request_body = vec![0; length];
+                reader.read_exact(&mut request_body).unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+                let _ = tx.send(Received {
+                    path,
+                    authorization,
+                    body: String::from_utf8(request_body).unwrap(),
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    fn listen(title: &str) -> Listen {
+        Listen {
+            listened_at: 1_700_000_000,
+            artist: "Artist".to_string(),
+            title: title.to_string(),
+            album: Some("Album".to_string()),
+            duration_ms: Some(200_000),
+        }
+    }
+
+    /// Build a worker submitting to `url` with queue saved in a temporary file named `name`.
+    fn worker(service: ScrobbleService, url: String, name: &str) -> Worker {
+        let path = std::env::temp_dir().join(format!(
+            "mpax-scrobble-test-{}-{name}.json",
+            std::process::id()
+        ));
+        Worker {
+            config: ScrobbleConfig {
+                service,
+                url: Some(url),
+                token: Some("token".to_string()),
+                api_key: Some("key".to_string()),
+                api_secret: Some("secret".to_string()),
+                session_key: Some("session".to_string()),
+            },
+            client: Client::builder().timeout(SUBMIT_TIMEOUT).build().unwrap(),
+            queue: Queue {
+                path: Some(path),
+                listens: vec![],
+            },
+        }
+    }
+
+    /// Decode `application/x-www-form-urlencoded` `body`.
+    fn decode_form(body: &str) -> Vec<(String, String)> {
+        let decode = |x: &str| {
+            let x = x.replace('+', " ");
+            let mut bytes = vec![];
+            let mut chars = x.bytes();
+            while let Some(c) = chars.next() {
+                if c == b'%' {
+                    let hex = [chars.next().unwrap(), chars.next().unwrap()];
+                    let hex = std::str::from_utf8(&hex).unwrap();
+                    bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                } else {
+                    bytes.push(c);
+                }
+            }
+            String::from_utf8(bytes).unwrap()
+        };
+        body.split('&')
+            .filter_map(|x| x.split_once('='))
+            .map(|(k, v)| (decode(k), decode(v)))
+            .collect()
+    }
+
+    #[test]
+    fn should_submit_after_half_or_four_minutes() {
+        let mut listen = listen("a");
+        listen.duration_ms = Some(200_000);
+        assert!(!listen.should_submit(Duration::from_secs(99)));
+        assert!(listen.should_submit(Duration::from_secs(100)));
+
+        listen.duration_ms = Some(600_000);
+        assert!(!listen.should_submit(LISTEN_THRESHOLD.saturating_sub(Duration::from_secs(1))));
+        assert!(listen.should_submit(LISTEN_THRESHOLD));
+
+        listen.duration_ms = Some(29_000);
+        assert!(!listen.should_submit(Duration::from_secs(29)));
+        listen.duration_ms = Some(30_000);
+        assert!(listen.should_submit(Duration::from_secs(15)));
+
+        listen.duration_ms = None;
+        assert!(!listen.should_submit(LISTEN_THRESHOLD.saturating_sub(Duration::from_secs(1))));
+        assert!(listen.should_submit(LISTEN_THRESHOLD));
+    }
+
+    #[test]
+    fn submit_listenbrainz_payload() {
+        let (url, rx) = serve(vec![(200, r#"{"status":"ok"}"#), (200, "{}")]);
+        let worker = worker(ScrobbleService::ListenBrainz, url, "lb-payload");
+
+        assert!(matches!(
+            worker.submit(&listen("a"), true),
+            Ok(Submission::Accepted)
+        ));
+        let received = rx.recv().unwrap();
+        assert_eq!(received.path, "/1/submit-listens");
+        assert_eq!(received.authorization.as_deref(), Some("Token token"));
+        let body: serde_json::Value = serde_json::from_str(received.body.as_str()).unwrap();
+        assert_eq!(body["listen_type"], "single");
+        let payload = &body["payload"][0];
+        assert_eq!(payload["listened_at"], 1_700_000_000);
+        assert_eq!(payload["track_metadata"]["artist_name"], "Artist");
+        assert_eq!(payload["track_metadata"]["track_name"], "a");
+        assert_eq!(payload["track_metadata"]["release_name"], "Album");
+        assert_eq!(
+            payload["track_metadata"]["additional_info"]["duration_ms"],
+            200_000
+        );
+
+        worker.submit(&listen("a"), false).unwrap();
+        let body: serde_json::Value =
+            serde_json::from_str(rx.recv().unwrap().body.as_str()).unwrap();
+        assert_eq!(body["listen_type"], "playing_now");
+        assert!(body["payload"][0].get("listened_at").is_none());
+    }
+
+    #[test]
+    fn submit_lastfm_payload_signed() {
+        let (url, rx) = serve(vec![(200, r#"{"scrobbles":{}}"#)]);
+        let worker = worker(ScrobbleService::LastFm, url, "lastfm-payload");
+
+        assert!(matches!(
+            worker.submit(&listen("a b"), true),
+            Ok(Submission::Accepted)
+        ));
+        let params = decode_form(rx.recv().unwrap().body.as_str());
+        let get = |k: &str| {
+            params
+                .iter()
+                .find(|x| x.0 == k)
+                .map(|x| x.1.as_str())
+                .unwrap()
+        };
+        assert_eq!(get("method"), "track.scrobble");
+        assert_eq!(get("track"), "a b");
+        assert_eq!(get("timestamp"), "1700000000");
+        assert_eq!(get("duration"), "200");
+        assert_eq!(get("format"), "json");
+        let signature = "albumAlbumapi_keykeyartistArtistduration200methodtrack.scrobble\
+            sksessiontimestamp1700000000tracka bsecret";
+        assert_eq!(
+            get("api_sig"),
+            format!("{:x}", md5::compute(signature)).as_str()
+        );
+    }
+
+    #[test]
+    fn flush_retries_or_drops_by_status() {
+        let cases = [
+            (500, "", true),
+            (401, "", true),
+            (403, r#"{"error":9}"#, true),
+            (429, "", true),
+            (200, r#"{"error":11}"#, true),
+            (400, "", false),
+            (404, "", false),
+            (200, r#"{"error":6}"#, false),
+        ];
+        for (i, (status, body, kept)) in cases.into_iter().enumerate() {
+            let (url, _rx) = serve(vec![(status, body)]);
+            let mut worker = worker(ScrobbleService::LastFm, url, format!("flush-{i}").as_str());
+            worker.queue.push(listen("a"));
+            worker.flush();
+            assert_eq!(
+                worker.queue.listens.len(),
+                usize::from(kept),
+                "status {status} with {body}"
+            );
+            let saved: Vec<Listen> = serde_json::from_str(
+                &fs::read_to_string(worker.queue.path.as_ref().unwrap()).unwrap(),
+            )
+            .unwrap();
+            assert_eq!(saved.len(), usize::from(kept));
+            let _ = fs::remove_file(worker.queue.path.as_ref().unwrap());
+        }
+    }
+
+    #[test]
+    fn flush_stops_at_first_failure() {
+        let (url, rx) = serve(vec![(200, "{}"), (503, "")]);
+        let mut worker = worker(ScrobbleService::ListenBrainz, url, "flush-order");
+        for title in ["a", "b", "c"] {
+            worker.queue.push(listen(title));
+        }
+        worker.flush();
+        let titles: Vec<_> = worker
+            .queue
+            .listens
+            .iter()
+            .map(|x| x.title.as_str())
+            .collect();
+        assert_eq!(titles, ["b", "c"]);
+        assert_eq!(rx.iter().count(), 2);
+        let _ = fs::remove_file(worker.queue.path.as_ref().unwrap());
+    }
+
+    #[test]
+    fn queue_drops_earliest_when_full() {
+        let mut queue = Queue {
+            path: None,
+            listens: vec![],
+        };
+        for i in 0..=MAX_QUEUED_LISTENS {
+            queue.push(listen(i.to_string().as_str()));
+        }
+        assert_eq!(queue.listens.len(), MAX_QUEUED_LISTENS);
+        assert_eq!(queue.listens[0].title, "1");
+    }
+}
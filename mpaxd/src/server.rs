@@ -1,53 +1,214 @@
+use std::convert::Infallible;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use axum::body::Body;
 use axum::extract::{Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
 use axum::http::StatusCode;
-use axum::response::Response;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
-use axum::Router;
-use log::{error, info};
+use axum::{Json, Router};
+use futures::{stream, StreamExt};
+use log::{error, info, warn};
 use racros::AutoDebug;
 use serde::Deserialize;
 use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::oneshot;
 
-use libmpax::api::{DEFAULT_SERVER_URL, ROUTE_ACTION_PAUSE, ROUTE_ACTION_PLAY};
+use libmpax::api::{
+    ROUTE_ACTION_AB_LOOP, ROUTE_ACTION_CROSSFADE, ROUTE_ACTION_MODE, ROUTE_ACTION_MUTE,
+    ROUTE_ACTION_NEXT, ROUTE_ACTION_PAUSE, ROUTE_ACTION_PLAY, ROUTE_ACTION_PREVIOUS,
+    ROUTE_ACTION_RESUME, ROUTE_ACTION_SEEK, ROUTE_ACTION_SLEEP, ROUTE_ACTION_STOP,
+    ROUTE_ACTION_VOLUME, ROUTE_ANALYSIS, ROUTE_HISTORY, ROUTE_PLAYLIST, ROUTE_STATUS, ROUTE_STREAM,
+};
+use libmpax::dto::{MusicSortKey, PlayMode as PlayModeModel, PlaylistEntryModel};
 
-use crate::player::PlayAction;
+use crate::config::ServerConfig;
+use crate::history::{History, HistoryQuery};
+use crate::network::is_network_url;
+use crate::output::HttpOutputs;
+use crate::player::{Crossfade, PlayAction, SeekPosition, Sleep, VolumeChange};
+
+/// Default count of records in a page of history.
+const HISTORY_DEFAULT_LIMIT: usize = 20;
+
+/// Max count of records in a page of history.
+const HISTORY_MAX_LIMIT: usize = 1000;
 
 #[derive(AutoDebug, Clone)]
 struct AppState {
     tx: Arc<Sender<PlayAction>>,
+
+    /// Outputs served on the server.
+    http: HttpOutputs,
+
+    /// Listening history.
+    history: History,
 }
 
 #[derive(AutoDebug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ActionPlayParam {
     file_path: Option<String>,
+
+    /// Url of network stream, only http(s) supported.
+    url: Option<String>,
+
+    /// Save [`Self::url`] as an internet radio station.
+    station: Option<bool>,
 }
 
 #[derive(AutoDebug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ActionPauseParam {}
 
+/// Parameters of playlist query.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaylistParam {
+    /// Field to sort music by, default is the order in playlist.
+    sort: Option<MusicSortKey>,
+
+    /// Sort in descending order.
+    desc: Option<bool>,
+}
+
+/// Parameters of history query.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryParam {
+    /// Only records at or after this time, in milliseconds since unix epoch.
+    since: Option<u64>,
+
+    /// Only records before this time, in milliseconds since unix epoch.
+    until: Option<u64>,
+
+    /// Count of latest records to skip, default is 0.
+    offset: Option<usize>,
+
+    /// Max count of records to return, default is [`HISTORY_DEFAULT_LIMIT`].
+    limit: Option<usize>,
+}
+
+/// Parameters of seek action.
+///
+/// Exactly one of `position` and `offset` should be provided.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionSeekParam {
+    /// Absolute position in milliseconds.
+    position: Option<u64>,
+
+    /// Offset in milliseconds relative to current position, negative value seeks backward.
+    offset: Option<i64>,
+}
+
+/// Parameters of volume action.
+///
+/// Exactly one of `volume` and `step` should be provided.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionVolumeParam {
+    /// Absolute volume in percent.
+    volume: Option<u8>,
+
+    /// Steps in percent relative to current volume, negative value turns down.
+    step: Option<i16>,
+}
+
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionModeParam {
+    mode: Option<String>,
+}
+
+/// Parameters of crossfade action.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionCrossfadeParam {
+    /// Crossfade length in milliseconds, zero disables crossfade.
+    duration: Option<u64>,
+
+    /// Also crossfade in repeat single mode.
+    repeat_single: Option<bool>,
+}
+
+/// Parameters of A-B loop action.
+///
+/// Provide both `start` and `end` to loop, or neither to stop looping.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionAbLoopParam {
+    /// Loop start in milliseconds.
+    start: Option<u64>,
+
+    /// Loop end in milliseconds.
+    end: Option<u64>,
+}
+
+/// Parameters of stop action.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionStopParam {
+    /// Stop at the end of current music instead of now.
+    after_current: Option<bool>,
+}
+
+/// Parameters of sleep action.
+#[derive(AutoDebug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActionSleepParam {
+    /// Stop after this time in milliseconds, absent or zero cancels the pending stop.
+    duration: Option<u64>,
+}
+
 /// Launch a thread for the socket to run on.
 ///
 /// Listen for the messages from client (mpaxctl) and act to it.
 ///
 /// This function takes a [Sender] type argument [tx] to send
 /// Operations to the [Player].
-pub async fn launch_server_thread(tx: Sender<PlayAction>) -> Result<()> {
+pub async fn launch_server_thread(
+    tx: Sender<PlayAction>,
+    config: ServerConfig,
+    http: HttpOutputs,
+    history: History,
+) -> Result<()> {
     info!("server thread start");
 
-    let app_state = Arc::new(AppState { tx: Arc::new(tx) });
+    let app_state = Arc::new(AppState {
+        tx: Arc::new(tx),
+        http,
+        history,
+    });
 
     let server = Router::new()
         .route(ROUTE_ACTION_PLAY, get(handle_action_play))
         .route(ROUTE_ACTION_PAUSE, get(handle_action_pause))
+        .route(ROUTE_ACTION_RESUME, get(handle_action_resume))
+        .route(ROUTE_ACTION_NEXT, get(handle_action_next))
+        .route(ROUTE_ACTION_PREVIOUS, get(handle_action_previous))
+        .route(ROUTE_ACTION_SEEK, get(handle_action_seek))
+        .route(ROUTE_ACTION_VOLUME, get(handle_action_volume))
+        .route(ROUTE_ACTION_MUTE, get(handle_action_mute))
+        .route(ROUTE_ACTION_MODE, get(handle_action_mode))
+        .route(ROUTE_ACTION_CROSSFADE, get(handle_action_crossfade))
+        .route(ROUTE_ACTION_AB_LOOP, get(handle_action_ab_loop))
+        .route(ROUTE_ACTION_STOP, get(handle_action_stop))
+        .route(ROUTE_ACTION_SLEEP, get(handle_action_sleep))
+        .route(ROUTE_STATUS, get(handle_status))
+        .route(ROUTE_STREAM, get(handle_stream))
+        .route(ROUTE_ANALYSIS, get(handle_analysis))
+        .route(ROUTE_HISTORY, get(handle_history))
+        .route(ROUTE_PLAYLIST, get(handle_playlist))
         .with_state(app_state);
-    let listener = TcpListener::bind(DEFAULT_SERVER_URL).await?;
+    info!("server listen on {}", config.address);
+    let listener = TcpListener::bind(config.address.as_str()).await?;
     axum::serve(listener, server).await?;
     info!("server thread exit");
     Ok(())
@@ -59,13 +220,35 @@ async fn handle_action_play(
 ) -> Response {
     info!("{ROUTE_ACTION_PLAY}: params={:#?}", params);
 
-    if let Some(Query(ActionPlayParam {
-        file_path: Some(file_path),
-    })) = params
-    {
-        info!("{ROUTE_ACTION_PLAY} file_path={file_path}");
+    let (path, station) = match params {
+        Some(Query(ActionPlayParam {
+            file_path: Some(file_path),
+            url: None,
+            station: None | Some(false),
+        })) => {
+            info!("{ROUTE_ACTION_PLAY} file_path={file_path}");
+            (Some(file_path), false)
+        }
+        Some(Query(ActionPlayParam {
+            file_path: None,
+            url: Some(url),
+            station,
+        })) if is_network_url(&url) => {
+            info!("{ROUTE_ACTION_PLAY} url={url}, station={station:?}");
+            (Some(url), station.unwrap_or(false))
+        }
+        _ => (None, false),
+    };
+
+    if let Some(path) = path {
         let tx = app_state.tx.clone();
-        if let Err(err) = tx.send(PlayAction::Play(String::from(file_path))) {
+        let result = if station {
+            tx.send(PlayAction::AddStation(path.clone()))
+                .and_then(|()| tx.send(PlayAction::Play(path)))
+        } else {
+            tx.send(PlayAction::Play(path))
+        };
+        if let Err(err) = result {
             error!("{ROUTE_ACTION_PLAY} error when handling root: {}", err);
             return Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
@@ -90,8 +273,365 @@ async fn handle_action_pause(
     params: Option<Query<ActionPauseParam>>,
 ) -> Response {
     info!("{ROUTE_ACTION_PAUSE} params = {:#?}", params);
-    if let Err(err) = app_state.tx.send(PlayAction::Pause) {
-        error!("{ROUTE_ACTION_PAUSE} error: {}", err);
+    send_action(&app_state, ROUTE_ACTION_PAUSE, PlayAction::Pause)
+}
+
+async fn handle_action_resume(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_ACTION_RESUME}");
+    send_action(&app_state, ROUTE_ACTION_RESUME, PlayAction::Resume)
+}
+
+async fn handle_action_next(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_ACTION_NEXT}");
+    send_action(&app_state, ROUTE_ACTION_NEXT, PlayAction::Next)
+}
+
+async fn handle_action_previous(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_ACTION_PREVIOUS}");
+    send_action(&app_state, ROUTE_ACTION_PREVIOUS, PlayAction::Previous)
+}
+
+async fn handle_action_seek(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionSeekParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_SEEK} params = {:#?}", params);
+    let position = match params {
+        Some(Query(ActionSeekParam {
+            position: Some(position),
+            offset: None,
+        })) => SeekPosition::Absolute(Duration::from_millis(position)),
+        Some(Query(ActionSeekParam {
+            position: None,
+            offset: Some(offset),
+        })) if offset >= 0 => SeekPosition::Forward(Duration::from_millis(offset.unsigned_abs())),
+        Some(Query(ActionSeekParam {
+            position: None,
+            offset: Some(offset),
+        })) => SeekPosition::Backward(Duration::from_millis(offset.unsigned_abs())),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("invalid argument"))
+                .unwrap();
+        }
+    };
+    send_action_with_reply(&app_state, ROUTE_ACTION_SEEK, |reply| PlayAction::Seek {
+        position,
+        reply,
+    })
+    .await
+}
+
+async fn handle_action_volume(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionVolumeParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_VOLUME} params = {:#?}", params);
+    let change = match params {
+        Some(Query(ActionVolumeParam {
+            volume: Some(volume),
+            step: None,
+        })) => VolumeChange::Absolute(volume),
+        Some(Query(ActionVolumeParam {
+            volume: None,
+            step: Some(step),
+        })) => {
+            let value = u8::try_from(step.unsigned_abs()).unwrap_or(u8::MAX);
+            if step >= 0 {
+                VolumeChange::Up(value)
+            } else {
+                VolumeChange::Down(value)
+            }
+        }
+        _ => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("invalid argument"))
+                .unwrap();
+        }
+    };
+    send_action(
+        &app_state,
+        ROUTE_ACTION_VOLUME,
+        PlayAction::SetVolume(change),
+    )
+}
+
+async fn handle_action_mute(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_ACTION_MUTE}");
+    send_action(&app_state, ROUTE_ACTION_MUTE, PlayAction::ToggleMute)
+}
+
+async fn handle_action_mode(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionModeParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_MODE} params = {:#?}", params);
+    let Some(play_mode) = params
+        .and_then(|Query(x)| x.mode)
+        .and_then(|x| PlayModeModel::try_from(x.as_str()).ok())
+    else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("invalid argument"))
+            .unwrap();
+    };
+    send_action(
+        &app_state,
+        ROUTE_ACTION_MODE,
+        PlayAction::SetPlayMode(play_mode.into()),
+    )
+}
+
+async fn handle_action_crossfade(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionCrossfadeParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_CROSSFADE} params = {:#?}", params);
+    let Some(Query(ActionCrossfadeParam {
+        duration: Some(duration),
+        repeat_single,
+    })) = params
+    else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from("invalid argument"))
+            .unwrap();
+    };
+    let crossfade = Crossfade {
+        duration: Duration::from_millis(duration),
+        repeat_single: repeat_single.unwrap_or(false),
+    };
+    send_action(
+        &app_state,
+        ROUTE_ACTION_CROSSFADE,
+        PlayAction::SetCrossfade(crossfade),
+    )
+}
+
+async fn handle_action_ab_loop(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionAbLoopParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_AB_LOOP} params = {:#?}", params);
+    let range = match params {
+        None
+        | Some(Query(ActionAbLoopParam {
+            start: None,
+            end: None,
+        })) => None,
+        Some(Query(ActionAbLoopParam {
+            start: Some(start),
+            end: Some(end),
+        })) if start < end => Some((Duration::from_millis(start), Duration::from_millis(end))),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("invalid argument"))
+                .unwrap();
+        }
+    };
+    send_action_with_reply(&app_state, ROUTE_ACTION_AB_LOOP, |reply| {
+        PlayAction::SetAbLoop { range, reply }
+    })
+    .await
+}
+
+async fn handle_action_stop(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionStopParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_STOP} params = {:#?}", params);
+    let action = match params {
+        Some(Query(ActionStopParam {
+            after_current: Some(true),
+        })) => PlayAction::SetSleep(Some(Sleep::AfterCurrent)),
+        _ => PlayAction::Stop,
+    };
+    send_action(&app_state, ROUTE_ACTION_STOP, action)
+}
+
+async fn handle_action_sleep(
+    State(app_state): State<Arc<AppState>>,
+    params: Option<Query<ActionSleepParam>>,
+) -> Response {
+    info!("{ROUTE_ACTION_SLEEP} params = {:#?}", params);
+    let sleep = params
+        .and_then(|Query(x)| x.duration)
+        .filter(|x| *x > 0)
+        .map(|x| Sleep::Timer(Duration::from_millis(x)));
+    send_action(&app_state, ROUTE_ACTION_SLEEP, PlayAction::SetSleep(sleep))
+}
+
+async fn handle_status(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_STATUS}");
+    let (tx, rx) = oneshot::channel();
+    let resp = send_action(&app_state, ROUTE_STATUS, PlayAction::QueryStatus(tx));
+    if resp.status() != StatusCode::OK {
+        return resp;
+    }
+    match rx.await {
+        Ok(status) => Json(status).into_response(),
+        Err(err) => {
+            error!("{ROUTE_STATUS} error: {}", err);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{err}")))
+                .unwrap()
+        }
+    }
+}
+
+/// Stream the audio playing in wav format, never ends.
+///
+/// Listeners falling behind skip some audio to catch up.
+async fn handle_stream(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_STREAM}");
+    let Some(http) = &app_state.http.stream else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("http stream output not enabled"))
+            .unwrap();
+    };
+    let (header, rx) = http.subscribe();
+    let data = stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(v) => return Some((Ok::<_, Infallible>(v), rx)),
+                Err(RecvError::Lagged(count)) => {
+                    warn!("{ROUTE_STREAM} listener lagged, skip {count} chunks");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    let body = stream::once(async { Ok(header) }).chain(data);
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "audio/wav")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::from_stream(body))
+        .unwrap()
+}
+
+/// Stream levels and spectrum of the audio playing as server-sent events, never ends.
+///
+/// Each event carries an [`AnalysisModel`] in json. Listeners falling behind skip some
+/// analyses to catch up.
+///
+/// [`AnalysisModel`]: libmpax::dto::AnalysisModel
+async fn handle_analysis(State(app_state): State<Arc<AppState>>) -> Response {
+    info!("{ROUTE_ANALYSIS}");
+    let Some(analyzer) = &app_state.http.analyzer else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("analyzer not enabled"))
+            .unwrap();
+    };
+    let events = stream::unfold(analyzer.subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(v) => match Event::default().json_data(v) {
+                    Ok(event) => return Some((Ok::<_, Infallible>(event), rx)),
+                    Err(e) => error!("{ROUTE_ANALYSIS} failed to encode analysis: {e}"),
+                },
+                Err(RecvError::Lagged(count)) => {
+                    warn!("{ROUTE_ANALYSIS} listener lagged, skip {count} analyses");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Query contents of current playlist, sorted as requested.
+async fn handle_playlist(
+    State(app_state): State<Arc<AppState>>,
+    params: Query<PlaylistParam>,
+) -> Response {
+    info!("{ROUTE_PLAYLIST}: params={:#?}", params);
+    let (tx, rx) = oneshot::channel();
+    let resp = send_action(&app_state, ROUTE_PLAYLIST, PlayAction::QueryPlaylist(tx));
+    if resp.status() != StatusCode::OK {
+        return resp;
+    }
+    match rx.await {
+        Ok(mut playlist) => {
+            sort_playlist(
+                &mut playlist.entries,
+                params.sort.unwrap_or_default(),
+                params.desc.unwrap_or(false),
+            );
+            Json(playlist).into_response()
+        }
+        Err(err) => {
+            error!("{ROUTE_PLAYLIST} error: {}", err);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{err}")))
+                .unwrap()
+        }
+    }
+}
+
+/// Sort `entries` by `key`, entries with equal key keep the order in playlist.
+fn sort_playlist(entries: &mut [PlaylistEntryModel], key: MusicSortKey, desc: bool) {
+    entries.sort_by(|a, b| {
+        let (a, b) = if desc { (b, a) } else { (a, b) };
+        match key {
+            MusicSortKey::Index => a.index.cmp(&b.index),
+            MusicSortKey::Name => a.music.file_name.cmp(&b.music.file_name),
+            MusicSortKey::PlayCount => a.music.play_count.cmp(&b.music.play_count),
+            MusicSortKey::SkipCount => a.music.skip_count.cmp(&b.music.skip_count),
+            MusicSortKey::LastPlayed => a.music.last_played_ms.cmp(&b.music.last_played_ms),
+            MusicSortKey::Added => a.music.added_ms.cmp(&b.music.added_ms),
+        }
+    });
+}
+
+/// Query listening history, the latest one first.
+async fn handle_history(
+    State(app_state): State<Arc<AppState>>,
+    params: Query<HistoryParam>,
+) -> Response {
+    info!("{ROUTE_HISTORY}: params={:#?}", params);
+    let query = HistoryQuery {
+        since_ms: params.since,
+        until_ms: params.until,
+        offset: params.offset.unwrap_or(0),
+        limit: params
+            .limit
+            .unwrap_or(HISTORY_DEFAULT_LIMIT)
+            .min(HISTORY_MAX_LIMIT),
+    };
+    let history = app_state.history.clone();
+    match tokio::task::spawn_blocking(move || history.query(&query)).await {
+        Ok(Ok(page)) => Json(page).into_response(),
+        Ok(Err(err)) => {
+            error!("{ROUTE_HISTORY} error: {err:#}");
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{err:#}")))
+                .unwrap()
+        }
+        Err(err) => {
+            error!("{ROUTE_HISTORY} error: {err}");
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{err}")))
+                .unwrap()
+        }
+    }
+}
+
+/// Send `action` to the [Player] and build the response according to the result.
+fn send_action(app_state: &AppState, route: &str, action: PlayAction) -> Response {
+    if let Err(err) = app_state.tx.send(action) {
+        error!("{route} error: {}", err);
         return Response::builder()
             .status(StatusCode::INTERNAL_SERVER_ERROR)
             .body(Body::from(format!("{err}")))
@@ -102,3 +642,33 @@ async fn handle_action_pause(
         .body(Body::empty())
         .unwrap()
 }
+
+/// Send the action built by `action` to the [Player] and build the response according to the
+/// result replied through the sender passed to `action`.
+///
+/// Actions rejected by the [Player] are replied with [`StatusCode::BAD_REQUEST`] and the reason.
+async fn send_action_with_reply(
+    app_state: &AppState,
+    route: &str,
+    action: impl FnOnce(oneshot::Sender<Result<()>>) -> PlayAction,
+) -> Response {
+    let (tx, rx) = oneshot::channel();
+    let resp = send_action(app_state, route, action(tx));
+    if resp.status() != StatusCode::OK {
+        return resp;
+    }
+    match rx.await {
+        Ok(Ok(())) => resp,
+        Ok(Err(err)) => Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from(format!("{err:#}")))
+            .unwrap(),
+        Err(err) => {
+            error!("{route} error: {}", err);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("{err}")))
+                .unwrap()
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+
+use racros::AutoDebug;
+use rand::seq::SliceRandom;
+
+use crate::playlist::Playlist;
+
+/// Max count of history kept in [`Shuffle`].
+const HISTORY_LIMIT: usize = 1000;
+
+/// Shuffled play order of a [`Playlist`], used in random play mode.
+///
+/// Every [`Music`](crate::music::Music) in playlist is played once before any of them repeats.
+#[derive(AutoDebug, Default)]
+pub struct Shuffle {
+    /// File paths of music waiting to play in current round.
+    ///
+    /// The next one is at the end.
+    queue: Vec<String>,
+
+    /// File paths of music already played in current round.
+    played: HashSet<String>,
+
+    /// File paths of played music, the latest one is at the end.
+    history: Vec<String>,
+
+    /// Version of the [`Playlist`] when [`Self::queue`] was built.
+    ///
+    /// `None` if never built.
+    version: Option<u64>,
+}
+
+impl Shuffle {
+    /// Construct.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the music at `file_path` started playing.
+    ///
+    /// Call this every time a music starts, whatever the play mode is.
+    pub fn played(&mut self, file_path: &str) {
+        self.queue.retain(|x| x != file_path);
+        self.played.insert(file_path.to_string());
+        if self.history.last().map(String::as_str) != Some(file_path) {
+            self.history.push(file_path.to_string());
+        }
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.drain(..self.history.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /// Get the file path of next music to play in `playlist`.
+    ///
+    /// * Re-shuffle the rest of current round if `playlist` changed since last time.
+    /// * Start a new round if all music in current round played.
+    /// * Return `None` if `playlist` is empty.
+    pub fn next(&mut self, playlist: &Playlist) -> Option<String> {
+        if self.version != Some(playlist.version()) {
+            self.reshuffle(playlist);
+        }
+        if self.queue.is_empty() {
+            self.new_round(playlist);
+        }
+        self.queue.pop()
+    }
+
+    /// Put back `file_path` got from [`Self::next`] but not played, make it the next one
+    /// again.
+    pub fn put_back(&mut self, file_path: String) {
+        if !self.played.contains(&file_path) {
+            self.queue.push(file_path);
+        }
+    }
+
+    /// Get the file path of the music played before the current one.
+    ///
+    /// The current one is put back as the next one to play.
+    ///
+    /// Return `None` if there is no history before the current one.
+    pub fn previous(&mut self) -> Option<String> {
+        if self.history.len() < 2 {
+            return None;
+        }
+        let current = self.history.pop()?;
+        let previous = self.history.pop()?;
+        self.played.remove(&current);
+        self.queue.push(current);
+        Some(previous)
+    }
+
+    /// Shuffle music in `playlist` not played in current round.
+    fn reshuffle(&mut self, playlist: &Playlist) {
+        self.queue = playlist
+            .file_paths()
+            .filter(|x| !self.played.contains(*x))
+            .map(ToString::to_string)
+            .collect();
+        self.queue.shuffle(&mut rand::thread_rng());
+        self.version = Some(playlist.version());
+    }
+
+    /// Start a new round with all music in `playlist`.
+    ///
+    /// Avoid playing the last played one again right away.
+    fn new_round(&mut self, playlist: &Playlist) {
+        self.played.clear();
+        self.reshuffle(playlist);
+        let last = self.history.last();
+        if self.queue.len() > 1 && self.queue.last() == last {
+            let len = self.queue.len();
+            self.queue.swap(0, len - 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(count: usize) -> Playlist {
+        let mut playlist = Playlist::new("test".to_string());
+        for i in 0..count {
+            playlist
+                .add_music_by_path(format!("http://localhost/{i}.mp3").as_str())
+                .unwrap();
+        }
+        playlist
+    }
+
+    /// Play the next `count` music in `shuffle`.
+    fn play(shuffle: &mut Shuffle, playlist: &Playlist, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| {
+                let next = shuffle.next(playlist).unwrap();
+                shuffle.played(next.as_str());
+                next
+            })
+            .collect()
+    }
+
+    fn sorted(mut file_paths: Vec<String>) -> Vec<String> {
+        file_paths.sort();
+        file_paths
+    }
+
+    #[test]
+    fn test_no_repeat_in_round() {
+        let playlist = playlist(5);
+        let all = sorted(playlist.file_paths().map(ToString::to_string).collect());
+        let mut shuffle = Shuffle::new();
+        assert_eq!(
+            Shuffle::new().next(&Playlist::new("empty".to_string())),
+            None
+        );
+
+        let mut last = None;
+        for _ in 0..20 {
+            let round = play(&mut shuffle, &playlist, 5);
+            assert_ne!(round.first(), last.as_ref());
+            last = round.last().cloned();
+            assert_eq!(sorted(round), all);
+        }
+    }
+
+    #[test]
+    fn test_reshuffle_on_playlist_changed() {
+        let mut playlist = playlist(4);
+        let mut shuffle = Shuffle::new();
+        let played = play(&mut shuffle, &playlist, 2);
+
+        playlist
+            .add_music_by_path("http://localhost/new.mp3")
+            .unwrap();
+        let rest = play(&mut shuffle, &playlist, 3);
+        let mut expected = playlist
+            .file_paths()
+            .filter(|x| !played.iter().any(|y| y == x))
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(sorted(rest), expected);
+
+        // A music got from next but not played comes out next again.
+        let next = shuffle.next(&playlist).unwrap();
+        shuffle.put_back(next.clone());
+        assert_eq!(shuffle.next(&playlist), Some(next));
+    }
+
+    #[test]
+    fn test_previous() {
+        let playlist = playlist(5);
+        let mut shuffle = Shuffle::new();
+        let first = play(&mut shuffle, &playlist, 1);
+        assert_eq!(shuffle.previous(), None);
+
+        let played = [first, play(&mut shuffle, &playlist, 2)].concat();
+        assert_eq!(shuffle.previous().as_ref(), Some(&played[1]));
+        shuffle.played(played[1].as_str());
+        // The one before the previous one is still in history.
+        assert_eq!(shuffle.previous().as_ref(), Some(&played[0]));
+        shuffle.played(played[0].as_str());
+        // Going back puts the current ones back in order.
+        assert_eq!(play(&mut shuffle, &playlist, 2), played[1..]);
+
+        // Not played in current round again before the round ends.
+        let rest = play(&mut shuffle, &playlist, 2);
+        assert!(rest.iter().all(|x| !played.contains(x)));
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use libmpax::dto::PlayMode;
+use log::info;
+use racros::AutoDebug;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+
+use crate::config::state_dir;
+use crate::music::Music;
+
+/// Name of the state file in state directory.
+const STATE_FILE_NAME: &str = "state.json";
+
+/// Playback state saved when daemon exits, restored on next startup.
+#[derive(AutoDebug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedState {
+    /// Name of current playlist.
+    pub playlist_name: String,
+
+    /// All music in current playlist.
+    pub music: Vec<Music>,
+
+    /// File path of current music, `None` if never played.
+    pub current: Option<String>,
+
+    /// Played position of current music in milliseconds.
+    pub position_ms: u64,
+
+    /// Play mode.
+    pub play_mode: PlayMode,
+
+    /// Volume in percent.
+    pub volume: u8,
+
+    /// Muted or not.
+    pub muted: bool,
+}
+
+impl SavedState {
+    /// Load the state saved last time.
+    ///
+    /// Return `None` if never saved.
+    ///
+    /// # Errors
+    ///
+    /// * When failed to read or parse the state file.
+    pub fn load() -> Result<Option<Self>> {
+        let Some(path) = state_path() else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            fs::read_to_string(&path).context(t!("state.failedToLoad", path = path.display()))?;
+        let state = serde_json::from_str(content.as_str())
+            .context(t!("state.failedToLoad", path = path.display()))?;
+        info!("load state from {}", path.display());
+        Ok(Some(state))
+    }
+
+    /// Save the state, replace the one saved last time.
+    ///
+    /// # Errors
+    ///
+    /// * When state directory not found.
+    /// * When failed to write the state file.
+    pub fn save(&self) -> Result<()> {
+        let path = state_path().context(t!("state.stateDirNotFound"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context(t!("state.failedToSave", path = path.display()))?;
+        }
+        // Write to a temporary file first to not leave a broken state file if interrupted.
+        let temp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_string(self)?;
+        fs::write(&temp_path, content)
+            .and_then(|()| fs::rename(&temp_path, &path))
+            .context(t!("state.failedToSave", path = path.display()))?;
+        Ok(())
+    }
+}
+
+/// Get the path of state file.
+fn state_path() -> Option<PathBuf> {
+    state_dir().map(|x| x.join(STATE_FILE_NAME))
+}
@@ -0,0 +1,164 @@
+//! Play audio end to end with the wav output backend.
+
+use std::fs;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use libmpax::api::{ROUTE_ACTION_MODE, ROUTE_ACTION_PLAY, ROUTE_STATUS};
+use libmpax::dto::{PlayerState, PlayerStatusModel};
+
+/// Sample rate of the fixture, same as the output so no resampling happens.
+const SAMPLE_RATE: u32 = 44100;
+
+/// Frames in the fixture, half a second.
+const FIXTURE_FRAMES: u32 = SAMPLE_RATE / 2;
+
+/// Max time to wait for the daemon.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kill the daemon if the test failed before it exited.
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Create an empty directory for this test.
+fn test_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mpaxd-wav-output-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write a stereo square wave of [`FIXTURE_FRAMES`] frames at half amplitude.
+fn write_fixture(path: &Path) {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    for i in 0..FIXTURE_FRAMES {
+        let sample = if i / 50 % 2 == 0 { 16384 } else { -16384 };
+        writer.write_sample(sample as i16).unwrap();
+        writer.write_sample(sample as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Find a free local port for the server.
+fn free_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+/// Send GET request to `route` with `query`, return the body.
+fn get(address: &str, route: &str, query: &[(&str, &str)]) -> reqwest::Result<String> {
+    reqwest::blocking::Client::new()
+        .get(format!("http://{address}{route}"))
+        .query(query)
+        .send()?
+        .error_for_status()?
+        .text()
+}
+
+/// Poll the player status until `f` returns true on it.
+fn wait_status(address: &str, f: impl Fn(&PlayerStatusModel) -> bool) -> PlayerStatusModel {
+    let start = Instant::now();
+    loop {
+        if let Ok(body) = get(address, ROUTE_STATUS, &[]) {
+            let status: PlayerStatusModel = serde_json::from_str(&body).unwrap();
+            if f(&status) {
+                return status;
+            }
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "timeout waiting for player status"
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_play_to_wav() {
+    let dir = test_dir();
+    let fixture = dir.join("fixture.wav");
+    let output = dir.join("output.wav");
+    write_fixture(&fixture);
+    let address = free_address();
+
+    let mut daemon = Daemon(
+        Command::new(env!("CARGO_BIN_EXE_mpaxd"))
+            .args(["-o", "wav", "--output-path"])
+            .arg(&output)
+            .args(["-a", &address])
+            .env("HOME", &dir)
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("XDG_STATE_HOME", &dir)
+            .env("XDG_DATA_HOME", &dir)
+            .spawn()
+            .unwrap(),
+    );
+    wait_status(&address, |_| true);
+    get(&address, ROUTE_ACTION_MODE, &[("mode", "sequential")]).unwrap();
+    get(
+        &address,
+        ROUTE_ACTION_PLAY,
+        &[("filePath", fixture.to_str().unwrap())],
+    )
+    .unwrap();
+    wait_status(&address, |x| x.state == PlayerState::Playing);
+    wait_status(&address, |x| x.state == PlayerState::Stopped);
+
+    // Exit on SIGTERM, the wav file is finalized then.
+    let pid = i32::try_from(daemon.0.id()).unwrap();
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+    let start = Instant::now();
+    let exit = loop {
+        if let Some(v) = daemon.0.try_wait().unwrap() {
+            break v;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "timeout waiting for daemon to exit"
+        );
+        thread::sleep(Duration::from_millis(50));
+    };
+    assert!(exit.success());
+
+    let reader = WavReader::open(&output).unwrap();
+    let spec = reader.spec();
+    assert_eq!(spec.channels, 2);
+    assert_eq!(spec.sample_rate, SAMPLE_RATE);
+    assert_eq!(spec.sample_format, SampleFormat::Float);
+    assert_eq!(spec.bits_per_sample, 32);
+    // Written at real-time speed from the first play until exit, silence around the fixture.
+    let frames = reader.duration();
+    assert!(
+        frames >= FIXTURE_FRAMES,
+        "output too short: {frames} frames"
+    );
+    let samples = reader
+        .into_samples::<f32>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(samples.len(), frames as usize * 2);
+    let audible = samples.iter().filter(|x| x.abs() > 0.1).count() / 2;
+    let expected = FIXTURE_FRAMES as usize;
+    assert!(
+        audible.abs_diff(expected) < expected / 20,
+        "expected about {expected} audible frames, got {audible}"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}